@@ -0,0 +1,86 @@
+//! Compara el recorrido lineal contra el BVH y contra el VoxelWorld renderizando
+//! la misma escena. Uso: `cargo bench` o `cargo bench -- <escena.ron>`.
+
+use nalgebra_glm::Vec3;
+use ray_tracing::bvh::Bvh;
+use ray_tracing::voxel::VoxelWorld;
+use ray_tracing::{render_parallel, FrameBuffer, Scene, SceneDescription};
+use std::error::Error;
+use std::process::ExitCode;
+use std::time::Instant;
+
+fn benchmark(description: &SceneDescription) -> Result<(), Box<dyn Error>> {
+    const FRAMES: u32 = 5;
+    let (width, height) = (800, 600);
+    let materials = description.materials()?;
+    let mut scene = description.build_with(&materials)?;
+
+    let build_start = Instant::now();
+    let bvh = Bvh::new(&scene.objects);
+    let build_time = build_start.elapsed();
+    let linear = Bvh::flat(&scene.objects);
+
+    let time_frames = |framebuffer: &mut FrameBuffer, scene: &Scene| {
+        let start = Instant::now();
+        for _ in 0..FRAMES {
+            render_parallel(framebuffer, scene);
+        }
+        start.elapsed() / FRAMES
+    };
+
+    let mut linear_frame = FrameBuffer::new(width, height);
+    scene.bvh = linear;
+    let linear_time = time_frames(&mut linear_frame, &scene);
+    let mut bvh_frame = FrameBuffer::new(width, height);
+    scene.bvh = bvh;
+    let bvh_time = time_frames(&mut bvh_frame, &scene);
+    let same_image = linear_frame.cast_buffer() == bvh_frame.cast_buffer();
+
+    println!("{} objects, {}x{}, {} frames", scene.objects.len(), width, height, FRAMES);
+    println!("bvh build:    {:?}", build_time);
+    println!("linear scan:  {:?} per frame", linear_time);
+    println!("bvh:          {:?} per frame", bvh_time);
+    println!("speedup:      {:.2}x", linear_time.as_secs_f64() / bvh_time.as_secs_f64());
+    println!("same image:   {}", same_image);
+    if !same_image {
+        return Err("the BVH and the linear scan rendered different images".into());
+    }
+
+    // Los bloques de la escena guardados como VoxelWorld y recorridos con DDA
+    let cubes = description.cubes(&materials)?;
+    if cubes.is_empty() {
+        return Ok(());
+    }
+    scene.set_objects(vec![Box::new(VoxelWorld::from_cubes(Vec3::new(-0.5, -0.5, -0.5), &cubes)?)]);
+    let mut voxel_frame = FrameBuffer::new(width, height);
+    let voxel_time = time_frames(&mut voxel_frame, &scene);
+    let matching = voxel_frame.cast_buffer().iter().zip(bvh_frame.cast_buffer()).filter(|(a, b)| **a == *b).count();
+    println!("voxel dda:    {:?} per frame", voxel_time);
+    println!("same pixels:  {:.2}%", 100.0 * matching as f64 / (width * height) as f64);
+
+    // Un terreno de mas de un millon de bloques
+    let mut terrain = VoxelWorld::new(Vec3::new(-0.5, -0.5, -0.5));
+    let dirt = terrain.add_block_type(cubes[0].material.clone())?;
+    terrain.fill([-256, -16, -256], [255, -1, 255], dirt);
+    let block_count = terrain.block_count();
+    scene.set_objects(vec![Box::new(terrain)]);
+    let terrain_time = time_frames(&mut voxel_frame, &scene);
+    println!("voxel dda, {} blocks: {:?} per frame", block_count, terrain_time);
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    // cargo bench pasa `--bench`; la escena es el primer argumento que no sea una opcion
+    let scene = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .unwrap_or_else(|| "scenes/pyramid.ron".to_string());
+
+    match SceneDescription::load(&scene).map_err(Box::from).and_then(|description| benchmark(&description)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
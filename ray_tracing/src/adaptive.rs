@@ -0,0 +1,124 @@
+use serde::Deserialize;
+use crate::spectrum::Spectrum;
+
+/// Settings for adaptive sampling: every pixel starts with `min_samples`, and
+/// the ones that are still noisy get `batch` more per round until they pass
+/// the error target, hit `max_samples` or the time budget runs out.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveSampling {
+    /// Error target: a pixel is done once the standard error of its mean
+    /// luminance is below this fraction of the mean.
+    pub threshold: f32,
+    pub min_samples: u32,
+    pub max_samples: u32,
+    /// Samples added to every unconverged pixel per round.
+    pub batch: u32,
+    /// Seconds after which no new round is started. The first round always runs.
+    pub time_budget: Option<f32>,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        AdaptiveSampling {
+            threshold: 0.02,
+            min_samples: 8,
+            max_samples: 256,
+            batch: 8,
+            time_budget: None,
+        }
+    }
+}
+
+// Piso para la luminancia media, para que los pixeles casi negros no se muestreen para siempre
+const MIN_MEAN_LUMINANCE: f32 = 0.01;
+
+impl AdaptiveSampling {
+    pub fn is_converged(&self, estimate: &PixelEstimate) -> bool {
+        estimate.count >= self.max_samples
+            || (estimate.count >= self.min_samples.max(2) && estimate.relative_error() <= self.threshold)
+    }
+}
+
+/// Running mean and luminance variance of the samples taken for one pixel.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelEstimate {
+    pub sum: Spectrum,
+    luminance_sum: f32,
+    luminance_squared_sum: f32,
+    pub count: u32,
+}
+
+impl PixelEstimate {
+    pub fn add(&mut self, sample: Spectrum) {
+        let luminance = sample.luminance();
+        self.sum += sample;
+        self.luminance_sum += luminance;
+        self.luminance_squared_sum += luminance * luminance;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> Spectrum {
+        if self.count == 0 {
+            Spectrum::black()
+        } else {
+            self.sum / self.count as f32
+        }
+    }
+
+    /// Unbiased sample variance of the luminance.
+    pub fn variance(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY;
+        }
+        let n = self.count as f32;
+        let mean = self.luminance_sum / n;
+        ((self.luminance_squared_sum - n * mean * mean) / (n - 1.0)).max(0.0)
+    }
+
+    /// Standard error of the mean luminance, relative to the mean itself.
+    pub fn relative_error(&self) -> f32 {
+        let n = self.count as f32;
+        let mean = self.luminance_sum / n;
+        (self.variance() / n).sqrt() / mean.max(MIN_MEAN_LUMINANCE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(samples: &[f32]) -> PixelEstimate {
+        let mut estimate = PixelEstimate::default();
+        for &value in samples {
+            estimate.add(Spectrum::gray(value));
+        }
+        estimate
+    }
+
+    #[test]
+    fn variance_and_relative_error_of_known_samples() {
+        let pixel = estimate(&[1.0, 3.0, 1.0, 3.0]);
+        assert_eq!(pixel.mean(), Spectrum::gray(2.0));
+        // Varianza muestral (n - 1): 4 / 3; error estandar sqrt(4 / 3 / 4), relativo a la media 2
+        assert!((pixel.variance() - 4.0 / 3.0).abs() < 1e-5);
+        assert!((pixel.relative_error() - (1.0f32 / 3.0).sqrt() / 2.0).abs() < 1e-5);
+        assert_eq!(estimate(&[5.0]).variance(), f32::INFINITY);
+    }
+
+    #[test]
+    fn stops_once_the_error_is_under_the_threshold() {
+        let settings = AdaptiveSampling { threshold: 0.05, min_samples: 4, max_samples: 64, batch: 4, time_budget: None };
+        // Constante: converge en cuanto llega al minimo, no antes
+        assert!(!settings.is_converged(&estimate(&[0.5; 3])));
+        assert!(settings.is_converged(&estimate(&[0.5; 4])));
+        // Ruidoso: sigue hasta el tope
+        let noisy: Vec<f32> = (0..32).map(|i| if i % 2 == 0 { 0.0 } else { 1.0 }).collect();
+        assert!(!settings.is_converged(&estimate(&noisy)));
+        let capped: Vec<f32> = (0..64).map(|i| if i % 2 == 0 { 0.0 } else { 1.0 }).collect();
+        assert!(settings.is_converged(&estimate(&capped)));
+        // Casi negro: el piso de luminancia evita muestrearlo para siempre
+        let dark: Vec<f32> = (0..16).map(|i| if i % 2 == 0 { 0.0 } else { 1e-4 }).collect();
+        assert!(settings.is_converged(&estimate(&dark)));
+    }
+}
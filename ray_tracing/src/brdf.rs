@@ -0,0 +1,168 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::material::Material;
+use crate::plane::tangent_frame;
+use crate::spectrum::Spectrum;
+
+/// Below this roughness the specular lobe is treated as a perfect mirror.
+pub const MIRROR_ROUGHNESS: f32 = 0.05;
+
+// Reflectancia a incidencia normal de los dielectricos sin indice propio (~1.5)
+const DEFAULT_F0: f32 = 0.04;
+
+/// GGX alpha for a perceptual roughness in [0, 1].
+pub fn alpha(roughness: f32) -> f32 {
+    let roughness = roughness.clamp(MIRROR_ROUGHNESS, 1.0);
+    roughness * roughness
+}
+
+/// Head-on reflectance of a dielectric with index `ior`, seen from air.
+pub fn dielectric_f0(ior: f32) -> f32 {
+    if ior <= 1.0 {
+        return DEFAULT_F0;
+    }
+    ((ior - 1.0) / (ior + 1.0)).powi(2)
+}
+
+/// Refractive index whose head-on reflectance from air is `f0`; inverse of
+/// `dielectric_f0`.
+pub fn ior_for_f0(f0: f32) -> f32 {
+    let root = f0.clamp(0.0, 0.99).sqrt();
+    (1.0 + root) / (1.0 - root)
+}
+
+/// Head-on specular color: gray for dielectrics, the base color for metals.
+pub fn specular_color(material: &Material, base_color: Spectrum) -> Spectrum {
+    let dielectric = Spectrum::gray(dielectric_f0(material.refractive_index));
+    dielectric * (1.0 - material.metallic) + base_color * material.metallic
+}
+
+pub fn fresnel_schlick(f0: Spectrum, cos_theta: f32) -> Spectrum {
+    let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    f0 + (Spectrum::gray(1.0) - f0) * weight
+}
+
+/// GGX (Trowbridge-Reitz) normal distribution.
+pub fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    a2 / (PI * d * d)
+}
+
+/// Height-correlated Smith masking-shadowing, divided by `4 n.l n.v` so the
+/// Cook-Torrance term is just `D * V * F`.
+pub fn smith_visibility(n_dot_v: f32, n_dot_l: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let v = n_dot_l * (n_dot_v * n_dot_v * (1.0 - a2) + a2).sqrt();
+    let l = n_dot_v * (n_dot_l * n_dot_l * (1.0 - a2) + a2).sqrt();
+    0.5 / (v + l).max(1e-6)
+}
+
+/// Share of a hit that goes through the surface instead of bouncing off it.
+pub fn transmission(material: &Material) -> f32 {
+    (1.0 - material.metallic) * material.transmission
+}
+
+/// Light reflected toward `view` from light arriving along `light`, per unit
+/// of light intensity: the Cook-Torrance BRDF times `PI * n.l`, so a white
+/// Lambertian surface facing the light gives about 1.0. With `specular` off
+/// only the diffuse lobe is evaluated, for when the caller samples the
+/// specular lobe on its own.
+pub fn evaluate(
+    material: &Material,
+    base_color: Spectrum,
+    normal: &Vec3,
+    view: &Vec3,
+    light: &Vec3,
+    specular: bool,
+) -> Spectrum {
+    let n_dot_l = normal.dot(light);
+    let n_dot_v = normal.dot(view);
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return Spectrum::black();
+    }
+    let half = (view + light).normalize();
+    let specular_color = specular_color(material, base_color);
+    let fresnel = fresnel_schlick(specular_color, view.dot(&half));
+
+    // Lo que no refleja la capa especular vista desde `view`, como en el muestreo del path tracer
+    let diffuse = base_color * (Spectrum::gray(1.0) - fresnel_schlick(specular_color, n_dot_v)) * (1.0 - material.metallic);
+    let mut reflected = diffuse;
+    if specular {
+        let alpha = alpha(material.roughness);
+        let d = ggx_distribution(normal.dot(&half).max(0.0), alpha);
+        let v = smith_visibility(n_dot_v, n_dot_l, alpha);
+        reflected += fresnel * (PI * d * v);
+    }
+    reflected * ((1.0 - transmission(material)) * n_dot_l)
+}
+
+/// Microfacet normal around `normal`, distributed as `D(h) * n.h`, from two
+/// numbers in [0, 1).
+pub fn sample_microfacet(normal: &Vec3, alpha: f32, u: f32, v: f32) -> Vec3 {
+    let (tangent, bitangent) = tangent_frame(normal);
+    let cos_theta = ((1.0 - u) / (1.0 + (alpha * alpha - 1.0) * u)).max(0.0).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * v;
+    (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + normal * cos_theta).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    /// Stratified directions over the hemisphere around +Y, each standing for
+    /// an equal solid angle of 2 PI / n^2.
+    fn hemisphere(n: usize) -> impl Iterator<Item = Vec3> {
+        (0..n * n).map(move |k| {
+            let (u, v) = (((k % n) as f32 + 0.5) / n as f32, ((k / n) as f32 + 0.5) / n as f32);
+            let cos_theta = 1.0 - u;
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let phi = 2.0 * PI * v;
+            Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin())
+        })
+    }
+
+    #[test]
+    fn ggx_distribution_is_normalized() {
+        let n = 400;
+        for alpha in [0.1, 0.3, 0.6, 1.0] {
+            // Integral de D(h) n.h sobre el hemisferio = 1
+            let total: f32 = hemisphere(n).map(|h| ggx_distribution(h.y, alpha) * h.y).sum::<f32>() * 2.0 * PI / (n * n) as f32;
+            assert!((total - 1.0).abs() < 0.02, "alpha {}: {}", alpha, total);
+        }
+    }
+
+    /// Share of the light arriving from every direction that `material`,
+    /// white, sends toward `view`.
+    fn white_albedo(material: &Material, view: &Vec3) -> f32 {
+        let n = 200;
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        // `evaluate` ya lleva el coseno y un factor PI: el albedo es su promedio por 2 PI / PI
+        hemisphere(n).map(|light| evaluate(material, Spectrum::gray(1.0), &normal, view, &light, true).r).sum::<f32>() * 2.0
+            / (n * n) as f32
+    }
+
+    #[test]
+    fn white_surfaces_reflect_no_more_than_they_receive() {
+        for (metallic, roughness) in [(0.0, 0.3), (0.0, 1.0), (1.0, 0.4), (1.0, 0.6), (1.0, 1.0)] {
+            let material = Material::pbr(Color::new(255, 255, 255), metallic, roughness, 1.5, 0.0);
+            for view_angle in [0.0f32, 0.7, 1.3] {
+                let albedo = white_albedo(&material, &Vec3::new(view_angle.sin(), view_angle.cos(), 0.0));
+                assert!(albedo <= 1.01, "metallic {} roughness {} at {}: {}", metallic, roughness, view_angle, albedo);
+            }
+        }
+    }
+
+    #[test]
+    fn ggx_loses_energy_only_as_it_gets_rough() {
+        let head_on = Vec3::new(0.0, 1.0, 0.0);
+        let metal = |roughness| Material::pbr(Color::new(255, 255, 255), 1.0, roughness, 1.5, 0.0);
+        let albedo = [0.4, 0.6, 0.8, 1.0].map(|roughness| white_albedo(&metal(roughness), &head_on));
+        assert!(albedo[0] > 0.95, "{:?}", albedo);
+        assert!(albedo.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", albedo);
+        // Con alpha = 1, D = 1 / PI y la integral de frente queda 1 - ln 2
+        assert!((albedo[3] - (1.0 - 2.0f32.ln())).abs() < 0.01, "{:?}", albedo);
+    }
+}
@@ -0,0 +1,266 @@
+use nalgebra_glm::Vec3;
+use crate::rayintersect::{RayIntersect, Intersect};
+
+// Cuantos objetos dejamos en una hoja antes de seguir dividiendo
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Builds a box from two opposite corners, in any order.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Aabb {
+            min: Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Slab test. Returns the distance at which the ray enters the box (0.0 if
+    /// the origin is already inside), or `None` if it misses.
+    pub fn hit(&self, origin: &Vec3, inv_dir: &Vec3) -> Option<f32> {
+        self.clip(origin, inv_dir).map(|(t_enter, _)| t_enter.max(0.0))
+    }
+
+    /// Raw entry and exit distances of the ray through the box.
+    pub fn clip(&self, origin: &Vec3, inv_dir: &Vec3) -> Option<(f32, f32)> {
+        let t1 = (self.min.x - origin.x) * inv_dir.x;
+        let t2 = (self.max.x - origin.x) * inv_dir.x;
+        let t3 = (self.min.y - origin.y) * inv_dir.y;
+        let t4 = (self.max.y - origin.y) * inv_dir.y;
+        let t5 = (self.min.z - origin.z) * inv_dir.z;
+        let t6 = (self.max.z - origin.z) * inv_dir.z;
+
+        let t_min = t1.min(t2).max(t3.min(t4)).max(t5.min(t6));
+        let t_max = t1.max(t2).min(t3.max(t4)).min(t5.max(t6));
+
+        if t_max < 0.0 || t_min > t_max {
+            None
+        } else {
+            Some((t_min, t_max))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    bounds: Aabb,
+    // En una hoja: primer indice dentro de `indices`. En un nodo interno: hijo derecho.
+    // El hijo izquierdo siempre esta justo despues del padre.
+    offset: usize,
+    // Cantidad de objetos en la hoja, 0 para nodos internos
+    count: usize,
+}
+
+/// Bounding volume hierarchy over a list of objects. It only stores indices,
+/// so the objects stay in the caller's `Vec` and are passed back in on every query.
+/// Objects without a bounding box (infinite planes) are kept aside and always tested.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new<T: RayIntersect>(objects: &[T]) -> Bvh {
+        Bvh::with_leaf_size(objects, MAX_LEAF_SIZE)
+    }
+
+    /// A single leaf holding every object, which is the same as the old linear scan.
+    pub fn flat<T: RayIntersect>(objects: &[T]) -> Bvh {
+        Bvh::with_leaf_size(objects, objects.len().max(1))
+    }
+
+    fn with_leaf_size<T: RayIntersect>(objects: &[T], leaf_size: usize) -> Bvh {
+        let boxes: Vec<Option<Aabb>> = objects.iter().map(|object| object.bounding_box()).collect();
+        let (indices, unbounded) = (0..objects.len()).partition(|&i| boxes[i].is_some());
+        let boxes: Vec<Aabb> = boxes.into_iter().map(|b| b.unwrap_or_else(Aabb::empty)).collect();
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * objects.len()),
+            indices,
+            unbounded,
+        };
+        if !bvh.indices.is_empty() {
+            bvh.build(&boxes, 0, bvh.indices.len(), leaf_size);
+        }
+        bvh
+    }
+
+    /// Box around everything in the hierarchy, `None` if there is nothing or
+    /// some object is unbounded.
+    pub fn bounds(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.nodes.first().map(|root| root.bounds)
+        } else {
+            None
+        }
+    }
+
+    fn build(&mut self, boxes: &[Aabb], start: usize, end: usize, leaf_size: usize) -> usize {
+        let node_index = self.nodes.len();
+        let bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::empty(), |acc, &i| acc.union(&boxes[i]));
+        self.nodes.push(BvhNode { bounds, offset: start, count: end - start });
+
+        if end - start <= leaf_size {
+            return node_index;
+        }
+
+        // Partimos por la mediana sobre el eje mas largo de los centroides
+        let centroid_bounds = self.indices[start..end].iter().fold(Aabb::empty(), |acc, &i| {
+            let c = boxes[i].centroid();
+            acc.union(&Aabb { min: c, max: c })
+        });
+        let extent = centroid_bounds.max - centroid_bounds.min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+
+        let mid = (start + end) / 2;
+        self.indices[start..end].select_nth_unstable_by(mid - start, |&a, &b| {
+            boxes[a].centroid()[axis].total_cmp(&boxes[b].centroid()[axis])
+        });
+
+        self.build(boxes, start, mid, leaf_size);
+        let right = self.build(boxes, mid, end, leaf_size);
+        self.nodes[node_index].offset = right;
+        self.nodes[node_index].count = 0;
+        node_index
+    }
+
+    /// Closest hit along the ray. Ties on distance go to the object that comes
+    /// first in `objects`, so the result matches a front-to-back linear scan.
+    pub fn intersect<T: RayIntersect>(&self, objects: &[T], origin: &Vec3, direction: &Vec3) -> Intersect {
+        let mut closest = Intersect::empty();
+        let mut closest_index = usize::MAX;
+        let mut zbuffer = f32::INFINITY;
+        let mut test = |i: usize, zbuffer: &mut f32| {
+            let hit = objects[i].ray_intersect(origin, direction);
+            if hit.is_intersecting
+                && (hit.distance < *zbuffer || (hit.distance == *zbuffer && i < closest_index))
+            {
+                *zbuffer = hit.distance;
+                closest_index = i;
+                closest = hit;
+            }
+        };
+
+        for &i in &self.unbounded {
+            test(i, &mut zbuffer);
+        }
+
+        let inv_dir = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match node.bounds.hit(origin, &inv_dir) {
+                Some(t) if t <= zbuffer => {}
+                _ => continue,
+            }
+
+            if node.count > 0 {
+                for &i in &self.indices[node.offset..node.offset + node.count] {
+                    test(i, &mut zbuffer);
+                }
+            } else {
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            }
+        }
+
+        closest
+    }
+
+    /// Whether anything is hit before `max_distance`. Stops at the first hit found.
+    pub fn occluded<T: RayIntersect>(&self, objects: &[T], origin: &Vec3, direction: &Vec3, max_distance: f32) -> bool {
+        let blocks = |i: usize| {
+            let hit = objects[i].ray_intersect(origin, direction);
+            hit.is_intersecting && hit.distance < max_distance
+        };
+
+        if self.unbounded.iter().any(|&i| blocks(i)) {
+            return true;
+        }
+
+        let inv_dir = Vec3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            match node.bounds.hit(origin, &inv_dir) {
+                Some(t) if t < max_distance => {}
+                _ => continue,
+            }
+
+            if node.count > 0 {
+                if self.indices[node.offset..node.offset + node.count].iter().any(|&i| blocks(i)) {
+                    return true;
+                }
+            } else {
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::sphere::Sphere;
+    use fastrand::Rng;
+
+    #[test]
+    fn closest_hit_matches_linear_scan() {
+        let mut rng = Rng::with_seed(7);
+        let mut point = || Vec3::new(rng.f32() * 20.0 - 10.0, rng.f32() * 20.0 - 10.0, rng.f32() * 20.0 - 10.0);
+        let spheres: Vec<Sphere> = (0..200).map(|_| Sphere::new(point(), 0.5, Material::black())).collect();
+        let bvh = Bvh::new(&spheres);
+        let linear = Bvh::flat(&spheres);
+
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin = point() * 2.0;
+            let direction = (point() - origin).normalize();
+            let expected = linear.intersect(&spheres, &origin, &direction);
+            let found = bvh.intersect(&spheres, &origin, &direction);
+            assert_eq!(found.is_intersecting, expected.is_intersecting);
+            if expected.is_intersecting {
+                assert_eq!(found.distance, expected.distance);
+                hits += 1;
+            }
+        }
+        assert!(hits > 0);
+    }
+}
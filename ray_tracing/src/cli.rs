@@ -0,0 +1,179 @@
+use ray_tracing::{Filter, Integrator, SamplePattern, ToneMap};
+use std::path::PathBuf;
+
+pub const DEFAULT_SCENE: &str = "scenes/pyramid.ron";
+
+pub const USAGE: &str = "\
+usage:
+  ray_tracing [scene.ron]                 open the interactive viewer
+  ray_tracing render <scene.ron> -o <out.png|out.exr> [options]
+
+render options:
+  -o, --output <path>   output file; the extension picks PNG or EXR
+  --width <px>          image width (default 800)
+  --height <px>         image height (default 600)
+  --night               render the night lighting
+  --time <hours>        time of day, for scenes with a `sun` (e.g. 6.5 for dawn);
+                        can't be combined with --night
+  --exposure <ev>       exposure compensation in stops (default: the scene's)
+  --tone-map <name>     clamp, reinhard or aces (default: the scene's)
+  --integrator <name>   whitted (default) or path
+  --samples <n>         paths per camera ray for the path tracer (default 16)
+  --emitter-samples <n> shadow rays toward each group of glowing blocks (default: the scene's)
+  --aa <n>              n x n camera rays per pixel (default: the scene's)
+  --aa-pattern <name>   stratified or random (default: the scene's)
+  --filter <name>       box, tent, mitchell or gaussian (default: the scene's)
+  --adaptive            sample noisy pixels more (implied by the three below)
+  --threshold <x>       adaptive error target, relative to pixel brightness
+  --max-samples <n>     adaptive cap on samples per pixel
+  --time-budget <s>     stop adding adaptive rounds after this many seconds
+  --heatmap <path>      also write the samples per pixel as a heatmap PNG
+
+viewer keys: arrows orbit, W/S zoom, N day/night, T/R time of day forward/back,
+             P whitted/path tracer";
+
+pub struct RenderOptions {
+    pub scene: String,
+    pub output: PathBuf,
+    pub width: usize,
+    pub height: usize,
+    pub night: bool,
+    pub time: Option<f32>,
+    pub exposure: Option<f32>,
+    pub tone_map: Option<ToneMap>,
+    pub integrator: Integrator,
+    pub samples: u32,
+    pub emitter_samples: Option<u32>,
+    pub aa_grid: Option<u32>,
+    pub aa_pattern: Option<SamplePattern>,
+    pub filter: Option<Filter>,
+    pub adaptive: bool,
+    pub threshold: Option<f32>,
+    pub max_samples: Option<u32>,
+    pub time_budget: Option<f32>,
+    pub heatmap: Option<PathBuf>,
+}
+
+pub enum Command {
+    View { scene: String },
+    Render(RenderOptions),
+    Help,
+}
+
+fn parse_size(flag: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    match value.parse::<usize>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!("{} must be a positive integer, got `{}`", flag, value)),
+    }
+}
+
+/// `value` as a number, turning away NaN and the infinities that `f32` parses too.
+fn parse_finite(value: &str) -> Option<f32> {
+    value.parse::<f32>().ok().filter(|number| number.is_finite())
+}
+
+fn parse_positive(flag: &str, value: Option<String>) -> Result<f32, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    match value.parse::<f32>() {
+        Ok(number) if number > 0.0 => Ok(number),
+        _ => Err(format!("{} must be a positive number, got `{}`", flag, value)),
+    }
+}
+
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    let first = args.next();
+    if first.as_deref() != Some("render") {
+        let mut scene = None;
+        for arg in first.into_iter().chain(args) {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+                _ if scene.is_none() => scene = Some(arg),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+        let scene = scene.unwrap_or_else(|| DEFAULT_SCENE.to_string());
+        return Ok(Command::View { scene });
+    }
+
+    let mut scene = None;
+    let mut output = None;
+    let mut width = 800;
+    let mut height = 600;
+    let mut night = false;
+    let mut time = None;
+    let mut exposure = None;
+    let mut tone_map = None;
+    let mut integrator = Integrator::default();
+    let mut samples = 16;
+    let mut emitter_samples = None;
+    let mut aa_grid = None;
+    let mut aa_pattern = None;
+    let mut filter = None;
+    let mut adaptive = false;
+    let mut threshold = None;
+    let mut max_samples = None;
+    let mut time_budget = None;
+    let mut heatmap = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(args.next().ok_or("-o needs a path")?)),
+            "--width" => width = parse_size("--width", args.next())?,
+            "--height" => height = parse_size("--height", args.next())?,
+            "--night" => night = true,
+            "--time" => {
+                let value = args.next().ok_or("--time needs a value")?;
+                let hours = parse_finite(&value).ok_or_else(|| format!("--time must be a number of hours, got `{}`", value))?;
+                time = Some(hours);
+            }
+            "--exposure" => {
+                let value = args.next().ok_or("--exposure needs a value")?;
+                let ev = parse_finite(&value).ok_or_else(|| format!("--exposure must be a number, got `{}`", value))?;
+                exposure = Some(ev);
+            }
+            "--tone-map" => tone_map = Some(args.next().ok_or("--tone-map needs a name")?.parse()?),
+            "--integrator" => integrator = args.next().ok_or("--integrator needs a name")?.parse()?,
+            "--samples" => samples = parse_size("--samples", args.next())? as u32,
+            "--emitter-samples" => emitter_samples = Some(parse_size("--emitter-samples", args.next())? as u32),
+            "--aa" => aa_grid = Some(parse_size("--aa", args.next())? as u32),
+            "--aa-pattern" => aa_pattern = Some(args.next().ok_or("--aa-pattern needs a name")?.parse()?),
+            "--filter" => filter = Some(args.next().ok_or("--filter needs a name")?.parse()?),
+            "--adaptive" => adaptive = true,
+            "--threshold" => threshold = Some(parse_positive("--threshold", args.next())?),
+            "--max-samples" => max_samples = Some(parse_size("--max-samples", args.next())? as u32),
+            "--time-budget" => time_budget = Some(parse_positive("--time-budget", args.next())?),
+            "--heatmap" => heatmap = Some(PathBuf::from(args.next().ok_or("--heatmap needs a path")?)),
+            "-h" | "--help" => return Ok(Command::Help),
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            _ if scene.is_none() => scene = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    if night && time.is_some() {
+        return Err("--night and --time both set the time of day; use only one".to_string());
+    }
+
+    Ok(Command::Render(RenderOptions {
+        scene: scene.ok_or("render needs a scene file")?,
+        output: output.ok_or("render needs an output file (-o)")?,
+        width,
+        height,
+        night,
+        time,
+        exposure,
+        tone_map,
+        integrator,
+        samples,
+        emitter_samples,
+        aa_grid,
+        aa_pattern,
+        filter,
+        adaptive: adaptive || threshold.is_some() || max_samples.is_some() || time_budget.is_some(),
+        threshold,
+        max_samples,
+        time_budget,
+        heatmap,
+    }))
+}
@@ -1,7 +1,7 @@
 use std::fmt;
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -24,9 +24,31 @@ impl Color {
         Color { r, g, b }
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
+
+    /// Approximate color of a black body at `kelvin` degrees (1000 K to 40000 K),
+    /// using Tanner Helland's fit. 6600 K is roughly white.
+    pub fn from_kelvin(kelvin: f32) -> Color {
+        let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+        let channel = |value: f32| value.clamp(0.0, 255.0).round() as u8;
+
+        let r = if t <= 66.0 { 255.0 } else { 329.698_73 * (t - 60.0).powf(-0.133_204_76) };
+        let g = if t <= 66.0 {
+            99.470_8 * t.ln() - 161.119_57
+        } else {
+            288.122_16 * (t - 60.0).powf(-0.075_514_846)
+        };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.517_73 * (t - 10.0).ln() - 305.044_8
+        };
+        Color::new(channel(r), channel(g), channel(b))
+    }
 }
 
 impl Add for Color {
@@ -52,6 +74,17 @@ impl Mul<f32> for Color {
     }
 }
 
+/// Component-wise product, as if both were fractions of 255: filtering a
+/// surface color through a light color.
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        let channel = |a: u8, b: u8| ((a as u16 * b as u16 + 127) / 255) as u8;
+        Color::new(channel(self.r, other.r), channel(self.g, other.g), channel(self.b, other.b))
+    }
+}
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
@@ -1,7 +1,9 @@
 use nalgebra_glm::{Vec3};
 use crate::rayintersect::{RayIntersect, Intersect};
 use crate::material::Material;
+use crate::bvh::Aabb;
 
+#[derive(Clone)]
 pub struct Cube {
     pub min: Vec3,
     pub max: Vec3,
@@ -9,6 +11,13 @@ pub struct Cube {
 }
 
 impl Cube {
+    /// Cube between two opposite corners, given in any order.
+    pub fn new(a: Vec3, b: Vec3, material: Material) -> Self {
+        // Las escenas a veces dan las esquinas en desorden
+        let bounds = Aabb::new(a, b);
+        Cube { min: bounds.min, max: bounds.max, material }
+    }
+
     pub fn get_uv(&self, hit_point: Vec3, normal: Vec3) -> (f32, f32) {
         // Debug print
    
@@ -25,41 +34,127 @@ impl Cube {
         
         
         
-        let (u, v) = match (normal.x.round() as i32, 
-                            normal.y.round() as i32, 
-                            normal.z.round() as i32) {
-            (1, 0, 0) => {  // Positive X face
-                (local_z, local_y)
-            },
-            (-1, 0, 0) => { // Negative X face
-                (1.0 - local_z, local_y)
-            },
-            (0, 1, 0) => {  // Positive Y face
-                (local_x, local_z)
-            },
-            (0, -1, 0) => { // Negative Y face
-                (local_x, 1.0 - local_z)
-            },
-            (0, 0, 1) => {  // Positive Z face
-                (1.0 - local_x, local_y)
-            },
-            (0, 0, -1) => { // Negative Z face
-                (local_x, local_y)
-            },
-            _ => {
-                (0.0, 0.0)
+        face_uv(Vec3::new(local_x, local_y, local_z), normal)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// Uniform point on the surface (by area) from three numbers in [0, 1).
+    /// Returns the point and the outward normal of its face.
+    pub fn sample_surface(&self, pick: f32, s: f32, t: f32) -> (Vec3, Vec3) {
+        let size = self.max - self.min;
+        // Area de cada par de caras opuestas, la que es perpendicular a cada eje
+        let areas = [size.y * size.z, size.z * size.x, size.x * size.y];
+        let mut target = pick * (areas[0] + areas[1] + areas[2]);
+        let mut axis = 2;
+        for (i, area) in areas.iter().enumerate() {
+            if target < *area {
+                axis = i;
+                break;
             }
-        };
-        
-    
-        
-        let final_u = u.clamp(0.0, 1.0);
-        let final_v = v.clamp(0.0, 1.0);
-        
-        
-        (final_u, final_v)
+            target -= area;
+        }
+        // La mitad inferior del rango elige la cara min, la superior la max
+        let positive = target >= areas[axis] * 0.5;
+
+        let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut point = self.min;
+        point[a] += s * size[a];
+        point[b] += t * size[b];
+        let mut normal = Vec3::zeros();
+        if positive {
+            point[axis] = self.max[axis];
+            normal[axis] = 1.0;
+        } else {
+            normal[axis] = -1.0;
+        }
+        (point, normal)
+    }
+}
+
+/// One of the six faces of a block, named as in Minecraft: north is -Z and
+/// east is +X. Declared in `Face::ALL` order, so `face as usize` indexes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Top,
+    Bottom,
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Top, Face::Bottom, Face::North, Face::South, Face::East, Face::West];
+
+    /// The face an axis-aligned `normal` points out of.
+    pub fn from_normal(normal: Vec3) -> Option<Face> {
+        match (normal.x.round() as i32, normal.y.round() as i32, normal.z.round() as i32) {
+            (0, 1, 0) => Some(Face::Top),
+            (0, -1, 0) => Some(Face::Bottom),
+            (0, 0, -1) => Some(Face::North),
+            (0, 0, 1) => Some(Face::South),
+            (1, 0, 0) => Some(Face::East),
+            (-1, 0, 0) => Some(Face::West),
+            _ => None,
+        }
+    }
+
+    pub fn is_side(self) -> bool {
+        !matches!(self, Face::Top | Face::Bottom)
+    }
+}
+
+/// UV mapping for an axis-aligned box face. `local` is the hit point inside the
+/// box scaled to [0, 1] on every axis; `normal` picks which face was hit.
+/// On the side faces v runs downward, so the top row of a texture (the grass
+/// edge of a grass block) ends up at the top of the block.
+pub fn face_uv(local: Vec3, normal: Vec3) -> (f32, f32) {
+    let (local_x, local_y, local_z) = (local.x, local.y, local.z);
+    let (u, v) = match (normal.x.round() as i32, 
+                        normal.y.round() as i32, 
+                        normal.z.round() as i32) {
+        (1, 0, 0) => {  // Positive X face
+            (local_z, 1.0 - local_y)
+        },
+        (-1, 0, 0) => { // Negative X face
+            (1.0 - local_z, 1.0 - local_y)
+        },
+        (0, 1, 0) => {  // Positive Y face
+            (local_x, local_z)
+        },
+        (0, -1, 0) => { // Negative Y face
+            (local_x, 1.0 - local_z)
+        },
+        (0, 0, 1) => {  // Positive Z face
+            (1.0 - local_x, 1.0 - local_y)
+        },
+        (0, 0, -1) => { // Negative Z face
+            (local_x, 1.0 - local_y)
+        },
+        _ => {
+            (0.0, 0.0)
+        }
+    };
+
+    (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0))
+}
+
+/// Directions in which `u` and `v` grow on the face with this `normal`, as
+/// laid out by `face_uv`. Normal maps are read in this frame.
+pub fn face_tangents(normal: Vec3) -> (Vec3, Vec3) {
+    match (normal.x.round() as i32, normal.y.round() as i32, normal.z.round() as i32) {
+        (1, 0, 0) => (Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, -1.0, 0.0)),
+        (-1, 0, 0) => (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, -1.0, 0.0)),
+        (0, 1, 0) => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+        (0, -1, 0) => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+        (0, 0, 1) => (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+        (0, 0, -1) => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+        _ => (Vec3::zeros(), Vec3::zeros()),
     }
-    
 }
 
 
@@ -90,20 +185,15 @@ impl RayIntersect for Cube {
 
         let hit_point = origin + ray_direction * distance;
         let normal = self.compute_normal(hit_point);
-        let epsilon = 1e-4;
-        let is_on_surface = 
-            (hit_point.x - self.min.x).abs() < epsilon || 
-            (hit_point.x - self.max.x).abs() < epsilon ||
-            (hit_point.y - self.min.y).abs() < epsilon || 
-            (hit_point.y - self.max.y).abs() < epsilon ||
-            (hit_point.z - self.min.z).abs() < epsilon || 
-            (hit_point.z - self.max.z).abs() < epsilon;
-        
-        if !is_on_surface {
-        }
-        
         let(u,v) = self.get_uv(hit_point, normal);
-        Intersect::new(hit_point, normal, distance, self.material.clone(), u, v)
+        let (tangent, bitangent) = face_tangents(normal);
+        Intersect::new(hit_point, normal, distance, self.material.on_face(normal), u, v)
+            .with_tangents(tangent, bitangent)
+            .emitter(self.material.emission_strength > 0.0)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
     }
 }
 
@@ -132,3 +222,36 @@ impl Cube {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn corners_in_any_order_give_the_same_cube() {
+        let (a, b) = (Vec3::new(1.0, 2.0, -1.0), Vec3::new(-1.0, 0.0, 1.0));
+        let cube = Cube::new(a, b, Material::black());
+        assert_eq!((cube.min, cube.max), (Vec3::new(-1.0, 0.0, -1.0), Vec3::new(1.0, 2.0, 1.0)));
+        let hit = cube.ray_intersect(&Vec3::new(0.3, 5.0, 0.2), &Vec3::new(0.0, -1.0, 0.0));
+        assert!(hit.is_intersecting);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit.distance - 3.0).abs() < 1e-5);
+        // Las muestras de la superficie caen sobre las caras y miran hacia afuera
+        for pick in [0.05, 0.3, 0.55, 0.8, 0.95] {
+            let (point, normal) = cube.sample_surface(pick, 0.5, 0.5);
+            let center = (cube.min + cube.max) * 0.5;
+            assert!(normal.dot(&(point - center)) > 0.0);
+            assert_eq!(cube.compute_normal(point), normal);
+        }
+    }
+
+    #[test]
+    fn only_glowing_cubes_are_hit_as_emitters() {
+        let ray = (Vec3::new(0.5, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let plain = Cube::new(Vec3::zeros(), Vec3::new(1.0, 1.0, 1.0), Material::black());
+        let glowing = Cube::new(Vec3::zeros(), Vec3::new(1.0, 1.0, 1.0), Material::black().with_emission(Color::new(255, 200, 80), 2.0));
+        assert!(!plain.ray_intersect(&ray.0, &ray.1).emitter);
+        assert!(glowing.ray_intersect(&ray.0, &ray.1).emitter);
+    }
+}
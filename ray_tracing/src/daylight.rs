@@ -0,0 +1,103 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::color::Color;
+use crate::light::{smoothstep, DirectionalLight};
+use crate::scene::Sky;
+use crate::spectrum::Spectrum;
+
+/// Sun and moon driven by a continuous time of day. The sun rises in `east` at
+/// 6:00, peaks at noon `tilt` radians away from the zenith and sets at 18:00;
+/// the moon is always on the opposite side of the sky.
+#[derive(Debug, Clone)]
+pub struct DayCycle {
+    /// Hours since midnight, in [0, 24).
+    pub time: f32,
+    pub east: Vec3,
+    pub tilt: f32,
+    pub sun_intensity: f32,
+    pub moon_intensity: f32,
+    /// Apparent radius of the sun and moon, in radians; bigger gives softer shadows.
+    pub angular_radius: f32,
+    pub shadow_samples: u32,
+    pub moon_color: Color,
+    /// Tint of the sky around sunrise and sunset.
+    pub dusk_color: Color,
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        DayCycle {
+            time: 12.0,
+            east: Vec3::new(1.0, 0.0, 0.0),
+            tilt: 30f32.to_radians(),
+            sun_intensity: 2.0,
+            moon_intensity: 0.3,
+            angular_radius: 0.0,
+            shadow_samples: 1,
+            moon_color: Color::new(170, 190, 255),
+            dusk_color: Color::new(250, 140, 70),
+        }
+    }
+}
+
+fn lerp(a: Spectrum, b: Spectrum, t: f32) -> Spectrum {
+    a * (1.0 - t) + b * t
+}
+
+// Temperatura del sol en el horizonte y ya alto en el cielo
+const HORIZON_KELVIN: f32 = 1800.0;
+const NOON_KELVIN: f32 = 5800.0;
+
+impl DayCycle {
+    pub fn set_time(&mut self, hours: f32) {
+        self.time = hours.rem_euclid(24.0);
+    }
+
+    /// Unit vector toward the sun. Its `y` is the sine of the sun's elevation.
+    pub fn sun_direction(&self) -> Vec3 {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let east = self.east.normalize();
+        let south = east.cross(&up).normalize();
+        let zenith = up * self.tilt.cos() + south * self.tilt.sin();
+        let angle = (self.time - 6.0) / 12.0 * PI;
+        (east * angle.cos() + zenith * angle.sin()).normalize()
+    }
+
+    pub fn is_day(&self) -> bool {
+        self.sun_direction().y > 0.0
+    }
+
+    /// 1.0 in full daylight, 0.0 at night, blending smoothly through dawn and dusk.
+    pub fn daylight(&self) -> f32 {
+        smoothstep(-0.1, 0.15, self.sun_direction().y)
+    }
+
+    /// Warm and dim near the horizon, white and bright once it is high.
+    pub fn sun(&self) -> DirectionalLight {
+        let direction = self.sun_direction();
+        let kelvin = HORIZON_KELVIN + (NOON_KELVIN - HORIZON_KELVIN) * smoothstep(0.0, 0.5, direction.y);
+        let intensity = self.sun_intensity * smoothstep(-0.02, 0.1, direction.y);
+        DirectionalLight::new(direction, Color::from_kelvin(kelvin), intensity)
+            .with_disc(self.angular_radius, self.shadow_samples)
+    }
+
+    pub fn moon(&self) -> DirectionalLight {
+        let direction = -self.sun_direction();
+        let intensity = self.moon_intensity * smoothstep(-0.02, 0.1, direction.y);
+        DirectionalLight::new(direction, self.moon_color, intensity)
+            .with_disc(self.angular_radius, self.shadow_samples)
+    }
+
+    /// Blends the sky's night and day colors, with a dusk glow while the sun is near the horizon.
+    pub fn sky_color(&self, sky: &Sky) -> Spectrum {
+        let elevation = self.sun_direction().y;
+        let base = lerp(Spectrum::from(sky.night_color), Spectrum::from(sky.day_color), self.daylight());
+        let glow = 0.6 * (-(elevation / 0.15).powi(2)).exp();
+        lerp(base, Spectrum::from(self.dusk_color), glow)
+    }
+
+    pub fn ambient(&self, sky: &Sky) -> f32 {
+        let daylight = self.daylight();
+        sky.night_ambient * (1.0 - daylight) + sky.day_ambient * daylight
+    }
+}
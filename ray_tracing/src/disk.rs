@@ -0,0 +1,51 @@
+use nalgebra_glm::Vec3;
+use crate::bvh::Aabb;
+use crate::material::Material;
+use crate::plane::{plane_distance, tangent_frame};
+use crate::rayintersect::{RayIntersect, Intersect};
+
+pub struct Disk {
+    pub center: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl Disk {
+    pub fn new(center: Vec3, normal: Vec3, radius: f32, material: Material) -> Self {
+        Disk { center, normal: normal.normalize(), radius, material }
+    }
+}
+
+impl RayIntersect for Disk {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let distance = match plane_distance(ray_origin, ray_direction, &self.center, &self.normal) {
+            Some(distance) => distance,
+            None => return Intersect::empty(),
+        };
+
+        let hit_point = ray_origin + ray_direction * distance;
+        let local = hit_point - self.center;
+        if local.magnitude_squared() > self.radius * self.radius {
+            return Intersect::empty();
+        }
+
+        // La textura cubre el cuadrado que encierra al disco
+        let (tangent, bitangent) = tangent_frame(&self.normal);
+        let u = 0.5 + local.dot(&tangent) / (2.0 * self.radius);
+        let v = 0.5 + local.dot(&bitangent) / (2.0 * self.radius);
+        Intersect::new(hit_point, self.normal, distance, self.material.clone(), u, v)
+            .with_tangents(tangent, bitangent)
+            .thin()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let n = self.normal;
+        let extent = Vec3::new(
+            self.radius * (1.0 - n.x * n.x).max(0.0).sqrt() + 1e-4,
+            self.radius * (1.0 - n.y * n.y).max(0.0).sqrt() + 1e-4,
+            self.radius * (1.0 - n.z * n.z).max(0.0).sqrt() + 1e-4,
+        );
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
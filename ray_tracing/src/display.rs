@@ -0,0 +1,143 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use serde::Deserialize;
+use crate::color::Color;
+use crate::spectrum::Spectrum;
+
+/// Curve that squeezes unbounded radiance into [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum ToneMap {
+    /// Cut everything above 1.0. Matches what the 8-bit pipeline used to do.
+    #[default]
+    Clamp,
+    /// `x / (1 + x)` per channel.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+impl ToneMap {
+    pub fn apply(self, radiance: Spectrum) -> Spectrum {
+        match self {
+            ToneMap::Clamp => radiance,
+            ToneMap::Reinhard => radiance.map(|x| x / (1.0 + x)),
+            ToneMap::Aces => radiance.map(|x| {
+                // La curva de Narkowicz espera la exposicion de referencia de ACES
+                let x = x * 0.6;
+                // El ajuste tiende a 2.51 / 2.43 y pasa de 1.0 en los brillos altos
+                ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).min(1.0)
+            }),
+        }
+    }
+}
+
+impl FromStr for ToneMap {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<ToneMap, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(ToneMap::Clamp),
+            "reinhard" => Ok(ToneMap::Reinhard),
+            "aces" => Ok(ToneMap::Aces),
+            _ => Err(format!("unknown tone map `{}` (expected clamp, reinhard or aces)", name)),
+        }
+    }
+}
+
+impl fmt::Display for ToneMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ToneMap::Clamp => "clamp",
+            ToneMap::Reinhard => "reinhard",
+            ToneMap::Aces => "aces",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How linear radiance becomes an 8-bit pixel: exposure, then tone mapping,
+/// then the sRGB transfer curve.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayTransform {
+    /// Exposure compensation in stops; every +1.0 doubles the brightness.
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        DisplayTransform { exposure: 0.0, tone_map: ToneMap::Clamp }
+    }
+}
+
+impl DisplayTransform {
+    pub fn apply(&self, radiance: Spectrum) -> Color {
+        let exposed = radiance * self.exposure.exp2();
+        self.tone_map.apply(exposed).map(srgb_encode).to_color()
+    }
+}
+
+/// sRGB OETF: linear [0, 1] to the non-linear value stored in 8-bit images.
+pub fn srgb_encode(linear: f32) -> f32 {
+    let linear = linear.clamp(0.0, 1.0);
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse of `srgb_encode`.
+pub fn srgb_decode(encoded: f32) -> f32 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// `srgb_decode` for every 8-bit value, so texture lookups don't pay a `powf`.
+pub fn srgb_decode_u8(value: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    let table = TABLE.get_or_init(|| std::array::from_fn(|i| srgb_decode(i as f32 / 255.0)));
+    table[value as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_round_trips_every_8_bit_value() {
+        let display = DisplayTransform::default();
+        for value in 0..=255u8 {
+            let color = Color::new(value, value, value);
+            assert_eq!(display.apply(Spectrum::from_srgb(color)), color);
+        }
+    }
+
+    #[test]
+    fn tone_maps_stay_in_range_and_keep_order() {
+        let inputs = [0.0, 0.01, 0.18, 0.5, 1.0, 4.0, 100.0];
+        for tone_map in [ToneMap::Reinhard, ToneMap::Aces] {
+            let outputs = inputs.map(|x| tone_map.apply(Spectrum::gray(x)).r);
+            assert!(outputs.iter().all(|y| (0.0..=1.0).contains(y)), "{} leaves [0, 1]: {:?}", tone_map, outputs);
+            assert!(outputs.windows(2).all(|pair| pair[0] < pair[1]), "{} is not increasing: {:?}", tone_map, outputs);
+        }
+        assert_eq!(ToneMap::Reinhard.apply(Spectrum::gray(1.0)), Spectrum::gray(0.5));
+        assert_eq!(ToneMap::Clamp.apply(Spectrum::gray(3.0)), Spectrum::gray(3.0));
+    }
+
+    #[test]
+    fn each_stop_of_exposure_doubles_the_radiance() {
+        let brighter = DisplayTransform { exposure: 1.0, tone_map: ToneMap::Clamp };
+        let darker = DisplayTransform { exposure: -1.0, tone_map: ToneMap::Clamp };
+        let mid = Color::new(128, 128, 128);
+        let linear = Spectrum::from_srgb(mid);
+        assert_eq!(brighter.apply(linear * 0.5), mid);
+        assert_eq!(darker.apply(linear * 2.0), mid);
+        assert_eq!(brighter.apply(Spectrum::gray(0.75)), Color::new(255, 255, 255));
+    }
+}
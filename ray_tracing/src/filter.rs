@@ -0,0 +1,200 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::Deserialize;
+
+/// Reconstruction filter that weights the samples taken around a pixel center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Filter {
+    /// Every sample inside the pixel counts the same.
+    #[default]
+    Box,
+    /// Linear falloff to zero one pixel away.
+    Tent,
+    /// Mitchell-Netravali with B = C = 1/3. Sharper than the Gaussian, with a
+    /// small negative lobe.
+    Mitchell,
+    /// Gaussian with alpha 2, shifted so it reaches zero at the radius.
+    Gaussian,
+}
+
+const GAUSSIAN_ALPHA: f32 = 2.0;
+
+impl Filter {
+    /// How far from the pixel center, in pixels, samples get any weight.
+    pub fn radius(self) -> f32 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Mitchell => 2.0,
+            Filter::Gaussian => 1.5,
+        }
+    }
+
+    /// Weight of a sample `dx`, `dy` pixels away from the center.
+    pub fn weight(self, dx: f32, dy: f32) -> f32 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(self, d: f32) -> f32 {
+        let d = d.abs();
+        let radius = self.radius();
+        if d > radius {
+            return 0.0;
+        }
+        match self {
+            Filter::Box => 1.0,
+            Filter::Tent => 1.0 - d,
+            Filter::Mitchell => mitchell(d, 1.0 / 3.0, 1.0 / 3.0),
+            Filter::Gaussian => (-GAUSSIAN_ALPHA * d * d).exp() - (-GAUSSIAN_ALPHA * radius * radius).exp(),
+        }
+    }
+}
+
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let (x2, x3) = (x * x, x * x * x);
+    let value = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+    } else {
+        (-b - 6.0 * c) * x3 + (6.0 * b + 30.0 * c) * x2 + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+    };
+    value / 6.0
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Filter, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "box" => Ok(Filter::Box),
+            "tent" => Ok(Filter::Tent),
+            "mitchell" => Ok(Filter::Mitchell),
+            "gaussian" => Ok(Filter::Gaussian),
+            _ => Err(format!("unknown filter `{}` (expected box, tent, mitchell or gaussian)", name)),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Filter::Box => "box",
+            Filter::Tent => "tent",
+            Filter::Mitchell => "mitchell",
+            Filter::Gaussian => "gaussian",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Where the N x N samples of a pixel go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum SamplePattern {
+    /// One jittered sample in each cell of an N x N grid.
+    #[default]
+    Stratified,
+    /// N * N samples anywhere in the filter's footprint.
+    Random,
+}
+
+impl FromStr for SamplePattern {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<SamplePattern, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "stratified" => Ok(SamplePattern::Stratified),
+            "random" => Ok(SamplePattern::Random),
+            _ => Err(format!("unknown sample pattern `{}` (expected stratified or random)", name)),
+        }
+    }
+}
+
+/// Supersampling settings for a final render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AntiAliasing {
+    /// N, for N x N camera rays per pixel. 1 turns antialiasing off: a single
+    /// ray through the pixel center.
+    pub grid: u32,
+    pub pattern: SamplePattern,
+    pub filter: Filter,
+}
+
+impl Default for AntiAliasing {
+    fn default() -> Self {
+        AntiAliasing { grid: 1, pattern: SamplePattern::Stratified, filter: Filter::Box }
+    }
+}
+
+impl AntiAliasing {
+    /// Offsets from the pixel center, in pixels, of every sample for one pixel.
+    /// `random` yields numbers in [0, 1).
+    pub fn offsets(&self, mut random: impl FnMut() -> f32) -> Vec<(f32, f32)> {
+        let n = self.grid.max(1);
+        if n == 1 && self.pattern == SamplePattern::Stratified {
+            return vec![(0.0, 0.0)];
+        }
+        let footprint = 2.0 * self.filter.radius();
+        let to_offset = |s: f32| (s - 0.5) * footprint;
+
+        let mut offsets = Vec::with_capacity((n * n) as usize);
+        for j in 0..n {
+            for i in 0..n {
+                let (s, t) = match self.pattern {
+                    SamplePattern::Stratified => ((i as f32 + random()) / n as f32, (j as f32 + random()) / n as f32),
+                    SamplePattern::Random => (random(), random()),
+                };
+                offsets.push((to_offset(s), to_offset(t)));
+            }
+        }
+        offsets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILTERS: [Filter; 4] = [Filter::Box, Filter::Tent, Filter::Mitchell, Filter::Gaussian];
+
+    #[test]
+    fn weights_are_symmetric_and_vanish_past_the_radius() {
+        for filter in FILTERS {
+            let radius = filter.radius();
+            for d in [0.0, 0.3, 0.5, 0.9, 1.4, 1.9] {
+                assert_eq!(filter.weight(d, 0.2), filter.weight(-d, -0.2), "{} at {}", filter, d);
+            }
+            assert!(filter.weight(0.0, 0.0) > 0.0);
+            assert_eq!(filter.weight(radius + 0.01, 0.0), 0.0, "{}", filter);
+            assert_eq!(filter.weight(0.0, radius + 0.01), 0.0, "{}", filter);
+        }
+        // La gaussiana desplazada llega a cero justo en el radio, sin escalon
+        assert!(Filter::Gaussian.weight(1.5, 0.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tent_and_mitchell_sum_to_one_over_whole_pixel_shifts() {
+        for filter in [Filter::Tent, Filter::Mitchell] {
+            for x in [0.0, 0.1, 0.25, 0.5, 0.8] {
+                let sum: f32 = (-3..=3).map(|k| filter.weight_1d(x - k as f32)).sum();
+                assert!((sum - 1.0).abs() < 1e-5, "{} at {}: {}", filter, x, sum);
+            }
+        }
+        // El lobulo negativo de Mitchell, entre uno y dos pixeles
+        assert!(Filter::Mitchell.weight_1d(1.5) < 0.0);
+    }
+
+    #[test]
+    fn stratified_offsets_put_one_sample_in_each_cell() {
+        let aa = AntiAliasing { grid: 4, pattern: SamplePattern::Stratified, filter: Filter::Tent };
+        let mut values = [0.0, 0.99].into_iter().cycle();
+        let offsets = aa.offsets(|| values.next().unwrap());
+        assert_eq!(offsets.len(), 16);
+        let cell = 2.0 * Filter::Tent.radius() / 4.0;
+        for (index, (dx, dy)) in offsets.into_iter().enumerate() {
+            let (i, j) = ((index % 4) as f32, (index / 4) as f32);
+            assert!(dx >= -1.0 + i * cell && dx < -1.0 + (i + 1.0) * cell, "dx {} outside cell {}", dx, i);
+            assert!(dy >= -1.0 + j * cell && dy < -1.0 + (j + 1.0) * cell, "dy {} outside cell {}", dy, j);
+        }
+        assert_eq!(AntiAliasing::default().offsets(|| 0.7), vec![(0.0, 0.0)]);
+    }
+}
@@ -1,10 +1,24 @@
 use crate::color::Color;
+use crate::display::DisplayTransform;
+use crate::spectrum::Spectrum;
+use image::{ImageResult, Rgb, Rgb32FImage, RgbImage};
+use std::path::Path;
+
 #[derive(Debug, Clone)]
 pub struct FrameBuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<Color>,
+    /// Linear radiance as the renderer produced it. `resolve` turns it into `buffer`.
+    pub hdr: Vec<Spectrum>,
+    /// Running sum of the passes added by `accumulate` since the last reset.
+    pub accumulation: Vec<Spectrum>,
+    pub sample_count: u32,
+    /// Camera samples each pixel got in the last `Renderer::render`.
+    pub sample_counts: Vec<u32>,
     pub background_color: Color,
+    /// Exposure, tone map and sRGB curve applied by `resolve`.
+    pub display: DisplayTransform,
     pub current_color: Color
 }
 
@@ -12,17 +26,24 @@ impl FrameBuffer {
     pub fn new (width: usize, height: usize) -> FrameBuffer {
         let default_color = Color::new(255,255,255);
         let buffer = vec![default_color; width*height];
+        let hdr = vec![Spectrum::from_srgb(default_color); width*height];
         FrameBuffer {
             width,
             height,
             buffer,
+            hdr,
+            accumulation: vec![Spectrum::black(); width*height],
+            sample_count: 0,
+            sample_counts: vec![0; width*height],
             background_color: default_color,
+            display: DisplayTransform::default(),
             current_color: default_color
         }
     }
 
     pub fn clear(&mut self){
         self.buffer.fill(self.background_color);
+        self.hdr.fill(Spectrum::from_srgb(self.background_color));
     }
 
     pub fn point(&mut self, x:usize, y:usize){
@@ -40,6 +61,40 @@ impl FrameBuffer {
     pub fn set_current_color(&mut self, color:Color){
         self.current_color = color;
     }
+    pub fn set_radiance(&mut self, x:usize, y:usize, radiance:Spectrum){
+        self.hdr[self.width * y + x] = radiance;
+    }
+
+    pub fn get_radiance(&self, x:usize, y:usize) -> Spectrum {
+        self.hdr[self.width * y + x]
+    }
+
+    /// Forgets every accumulated pass. Call it whenever the camera or the lighting changes.
+    pub fn reset_accumulation(&mut self) {
+        self.accumulation.fill(Spectrum::black());
+        self.sample_count = 0;
+    }
+
+    /// Adds one sample per pixel to the accumulation buffer, then shows the mean
+    /// of everything accumulated so far.
+    pub fn accumulate(&mut self, samples: &[Spectrum]) {
+        self.sample_count += 1;
+        let scale = 1.0 / self.sample_count as f32;
+        for ((sum, pixel), sample) in self.accumulation.iter_mut().zip(self.hdr.iter_mut()).zip(samples) {
+            *sum += *sample;
+            *pixel = *sum * scale;
+        }
+        self.resolve();
+    }
+
+    /// Display step: runs the HDR buffer through `display` into the 8-bit
+    /// `buffer` that gets shown or written as PNG.
+    pub fn resolve(&mut self) {
+        for (pixel, radiance) in self.buffer.iter_mut().zip(&self.hdr) {
+            *pixel = self.display.apply(*radiance);
+        }
+    }
+
     pub fn cast_buffer(&self) -> Vec<u32> {
         let mut casted_vector: Vec<u32> = Vec::with_capacity(self.buffer.len());
         for color in &self.buffer {
@@ -47,4 +102,72 @@ impl FrameBuffer {
         }
         casted_vector
     }
-}
\ No newline at end of file
+
+    /// Writes the frame to disk. `.exr` files get the linear HDR buffer as 32-bit
+    /// floats; any other extension gets the 8-bit display buffer (PNG, JPEG, ...).
+    pub fn save(&self, path: &Path) -> ImageResult<()> {
+        let (width, height) = (self.width as u32, self.height as u32);
+        let is_exr = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("exr"));
+
+        if is_exr {
+            let image = Rgb32FImage::from_fn(width, height, |x, y| {
+                let radiance = self.hdr[self.width * y as usize + x as usize];
+                Rgb([radiance.r, radiance.g, radiance.b])
+            });
+            image.save(path)
+        } else {
+            let image = RgbImage::from_fn(width, height, |x, y| {
+                let color = self.buffer[self.width * y as usize + x as usize];
+                Rgb([color.r, color.g, color.b])
+            });
+            image.save(path)
+        }
+    }
+
+    /// Debug AOV: `sample_counts` as a heatmap, from black (fewest samples)
+    /// through blue, green and yellow to red (most samples).
+    pub fn sample_heatmap(&self) -> Vec<Color> {
+        const STOPS: [(f32, f32, f32); 5] = [
+            (0.0, 0.0, 0.0),
+            (0.0, 0.0, 255.0),
+            (0.0, 255.0, 0.0),
+            (255.0, 255.0, 0.0),
+            (255.0, 0.0, 0.0),
+        ];
+        let min = self.sample_counts.iter().copied().min().unwrap_or(0);
+        let max = self.sample_counts.iter().copied().max().unwrap_or(0);
+        let range = (max - min).max(1) as f32;
+        self.sample_counts
+            .iter()
+            .map(|&count| {
+                let t = (count - min) as f32 / range * (STOPS.len() - 1) as f32;
+                let i = (t.floor() as usize).min(STOPS.len() - 2);
+                let f = t - i as f32;
+                let (a, b) = (STOPS[i], STOPS[i + 1]);
+                let lerp = |a: f32, b: f32| (a + (b - a) * f).round() as u8;
+                Color::new(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+            })
+            .collect()
+    }
+
+    pub fn save_sample_heatmap(&self, path: &Path) -> ImageResult<()> {
+        let heatmap = self.sample_heatmap();
+        let image = RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = heatmap[self.width * y as usize + x as usize];
+            Rgb([color.r, color.g, color.b])
+        });
+        image.save(path)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heatmap_spans_the_fewest_to_the_most_samples() {
+        let mut framebuffer = FrameBuffer::new(3, 1);
+        framebuffer.sample_counts = vec![64, 160, 256];
+        let heatmap = framebuffer.sample_heatmap();
+        assert_eq!(heatmap, vec![Color::new(0, 0, 0), Color::new(0, 255, 0), Color::new(255, 0, 0)]);
+    }
+}
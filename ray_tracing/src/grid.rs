@@ -0,0 +1,86 @@
+use nalgebra_glm::Vec3;
+use crate::cube::Cube;
+use crate::material::Material;
+
+//mode: se refiera a si queremos crear la cuadrícula sobre los ejex xy o yz
+//grid_size: se refiera al tamaño que se quiere para la cuadrícula
+pub fn create_grid(initial_min_position: &mut Vec3, initial_max_position: &mut Vec3, cube_length: f32, mode:usize, grid_size: usize, material:Material ) -> Vec<Cube>{
+    let mut material_grid: Vec<Cube> = Vec::new();
+    let mut current_min = *initial_min_position; // Dereference to get the actual value
+    let mut current_max = *initial_max_position;
+    let vertical_sum_vector = match mode {
+        1 => Vec3::new(cube_length, 0.0, 0.0), //Cuando queremos usar la cuadrícula en xy
+        2 => Vec3::new(cube_length, 0.0, 0.0),
+        _ => Vec3::new(0.0, 0.0, 0.0) //Caso base
+    };
+
+    let horizontal_sum_vector = match mode {
+        1 => Vec3::new(0.0, cube_length, 0.0), //Cuando queremos usar la cuadrícula en xy
+        2 => Vec3::new(0.0, 0.0, cube_length),
+        _ => Vec3::new(0.0, 0.0, 0.0) //Caso base
+    };
+
+    for _ in 0..grid_size + 1 {
+
+        for _ in 0..grid_size +1 {
+            material_grid.push(Cube::new(current_min, current_max, material.clone()));
+
+            current_max += vertical_sum_vector;
+            current_min += vertical_sum_vector;
+        }
+        *initial_min_position -= horizontal_sum_vector;
+        *initial_max_position -= horizontal_sum_vector;
+        current_min = *initial_min_position;
+        current_max = * initial_max_position;
+        
+
+
+    }
+
+    material_grid
+}
+
+pub fn create_empty_grid(
+    initial_min_position: &mut Vec3,
+    initial_max_position: &mut Vec3,
+    cube_length: f32,
+    mode: usize,
+    grid_size: usize,
+    material: Material,
+) -> Vec<Cube> {
+    let mut material_grid: Vec<Cube> = Vec::new();
+    let mut current_min = *initial_min_position; // Dereference to get the actual value
+    let mut current_max = *initial_max_position;
+
+    let vertical_sum_vector = match mode {
+        1 => Vec3::new(cube_length, 0.0, 0.0), // For XY grid
+        2 => Vec3::new(cube_length, 0.0, 0.0), // For XZ grid
+        _ => Vec3::new(0.0, 0.0, 0.0),         // Base case
+    };
+
+    let horizontal_sum_vector = match mode {
+        1 => Vec3::new(0.0, cube_length, 0.0), // For XY grid
+        2 => Vec3::new(0.0, 0.0, cube_length), // For XZ grid
+        _ => Vec3::new(0.0, 0.0, 0.0),         // Base case
+    };
+
+    for i in 0..=grid_size {
+        for j in 0..=grid_size {
+            // Only add cubes if they are on the grid's boundaries
+            if i == 0 || j == 0 || i == grid_size || j == grid_size {
+                material_grid.push(Cube::new(current_min, current_max, material.clone()));
+            }
+
+            current_max += vertical_sum_vector;
+            current_min += vertical_sum_vector;
+        }
+
+        // Move to the next row or column in the grid
+        *initial_min_position -= horizontal_sum_vector;
+        *initial_max_position -= horizontal_sum_vector;
+        current_min = *initial_min_position;
+        current_max = *initial_max_position;
+    }
+
+    material_grid
+}
@@ -0,0 +1,49 @@
+//! Minecraft-style ray tracer. Scenes are loaded from RON files (see
+//! [`SceneDescription`]) or built by hand from anything that implements
+//! [`RayIntersect`], and rendered into a [`FrameBuffer`] by a [`Renderer`].
+
+pub mod adaptive;
+pub mod brdf;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod cube;
+pub mod daylight;
+pub mod disk;
+pub mod display;
+pub mod filter;
+pub mod framebuffer;
+pub mod grid;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod obj;
+pub mod plane;
+pub mod quad;
+pub mod rayintersect;
+pub mod renderer;
+pub mod sampler;
+pub mod scene;
+pub mod spectrum;
+pub mod sphere;
+pub mod texture;
+pub mod triangle;
+pub mod voxel;
+
+pub use adaptive::AdaptiveSampling;
+pub use camera::Camera;
+pub use color::Color;
+pub use cube::Face;
+pub use daylight::DayCycle;
+pub use display::{DisplayTransform, ToneMap};
+pub use filter::{AntiAliasing, Filter, SamplePattern};
+pub use framebuffer::FrameBuffer;
+pub use grid::{create_empty_grid, create_grid};
+pub use light::{attenuation, AreaLight, AreaShape, DirectionalLight, EmitterLight, Light, LightSample, LightSource, SpotLight};
+pub use material::{AlphaMode, Material, TextureSlot};
+pub use rayintersect::{Intersect, RayIntersect};
+pub use renderer::{cast_ray, fresnel, reflect, refract, render_parallel, trace_path, Integrator, RayCone, Renderer};
+pub use sampler::{Sampler, TextureFilter, WrapMode};
+pub use scene::{Scene, SceneDescription, SceneError, Sky};
+pub use spectrum::Spectrum;
+pub use texture::{Texture, TextureRegion};
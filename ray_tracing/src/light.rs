@@ -1,10 +1,47 @@
 use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
 use crate::color::Color;
+use crate::cube::Cube;
+use crate::plane::tangent_frame;
 
+/// One shadow ray's worth of light, as seen from a shading point.
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Unit vector from the shading point toward the light.
+    pub direction: Vec3,
+    /// Distance to the sampled point on the light; shadow rays stop there.
+    pub distance: f32,
+    /// Intensity arriving along `direction`, before the surface's cosine.
+    pub intensity: f32,
+}
+
+/// Anything the shading loop can gather direct light from.
+pub trait LightSource: Send + Sync {
+    /// Picks a point on the light to illuminate `point` from. `u` and `v` are in
+    /// [0, 1) and stratified by the caller when it takes several samples.
+    /// `pick` is a third independent number in [0, 1), for lights that first
+    /// choose a part of themselves, such as the face of a cube.
+    fn sample(&self, point: &Vec3, u: f32, v: f32, pick: f32) -> LightSample;
+
+    fn color(&self) -> Color;
+
+    /// Overall brightness, for weighing lights against each other without
+    /// sampling them: the intensity, times the glowing area for lights with one.
+    fn power(&self) -> f32;
+
+    /// Shadow rays to average for every shading point. Point-like lights need one.
+    fn shadow_samples(&self) -> u32 {
+        1
+    }
+}
+
+/// Point light. `intensity` is what arrives one unit away; it falls off with
+/// the square of the distance and, if `range` is set, fades out completely there.
 pub struct Light {
     pub position: Vec3,
     pub color: Color,
     pub intensity: f32,
+    pub range: Option<f32>,
 }
 
 impl Light {
@@ -13,6 +50,461 @@ impl Light {
             position,
             color,
             intensity,
+            range: None,
+        }
+    }
+
+    /// White-ish point light of a blackbody at `kelvin`: about 1900 for a
+    /// candle or a torch, 2700 for a household bulb, 6500 for daylight.
+    pub fn from_kelvin(position: Vec3, kelvin: f32, intensity: f32) -> Self {
+        Light::new(position, Color::from_kelvin(kelvin), intensity)
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+}
+
+pub(crate) fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Direction and distance from `point` to `target`.
+fn toward(point: &Vec3, target: &Vec3) -> (Vec3, f32) {
+    let offset = target - point;
+    let distance = offset.magnitude();
+    (offset / distance, distance)
+}
+
+// Distancia minima para la atenuacion, asi un punto pegado a la luz no explota
+const MIN_DISTANCE: f32 = 0.1;
+
+/// Inverse-square falloff. With a `range` the light is windowed so it reaches
+/// exactly zero there instead of being cut off with a visible edge.
+pub fn attenuation(distance: f32, range: Option<f32>) -> f32 {
+    let falloff = 1.0 / distance.max(MIN_DISTANCE).powi(2);
+    match range {
+        Some(range) => {
+            let window = (1.0 - (distance / range).powi(4)).clamp(0.0, 1.0);
+            falloff * window * window
+        }
+        None => falloff,
+    }
+}
+
+impl LightSource for Light {
+    fn sample(&self, point: &Vec3, _u: f32, _v: f32, _pick: f32) -> LightSample {
+        let (direction, distance) = toward(point, &self.position);
+        let intensity = self.intensity * attenuation(distance, self.range);
+        LightSample { direction, distance, intensity }
+    }
+
+    fn color(&self) -> Color {
+        self.color
+    }
+
+    fn power(&self) -> f32 {
+        self.intensity
+    }
+}
+
+/// Point light that only shines inside a cone, like a lantern or a flashlight.
+/// Full intensity within `inner_angle` of `direction`, fading smoothly to zero
+/// at `outer_angle`; attenuates with distance like `Light`.
+pub struct SpotLight {
+    pub position: Vec3,
+    /// Unit vector the cone points along.
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    /// Half-angles of the cone, in radians.
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub range: Option<f32>,
+}
+
+impl SpotLight {
+    pub fn new(position: Vec3, direction: Vec3, color: Color, intensity: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            inner_angle: inner_angle.min(outer_angle),
+            outer_angle,
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// 1.0 inside the inner cone, 0.0 outside the outer one.
+    pub fn cone_falloff(&self, to_point: &Vec3) -> f32 {
+        let cos_angle = self.direction.dot(to_point);
+        smoothstep(self.outer_angle.cos(), self.inner_angle.cos(), cos_angle)
+    }
+}
+
+impl LightSource for SpotLight {
+    fn sample(&self, point: &Vec3, _u: f32, _v: f32, _pick: f32) -> LightSample {
+        let (direction, distance) = toward(point, &self.position);
+        let intensity = self.intensity * self.cone_falloff(&-direction) * attenuation(distance, self.range);
+        LightSample { direction, distance, intensity }
+    }
+
+    fn color(&self) -> Color {
+        self.color
+    }
+
+    fn power(&self) -> f32 {
+        self.intensity
+    }
+}
+
+/// Light from infinitely far away, like the sun or the moon: shadow rays are
+/// parallel and nothing attenuates it. A non-zero `angular_radius` spreads the
+/// shadow rays over the light's disc in the sky, which softens the shadows.
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// Unit vector pointing from the scene toward the light.
+    pub direction: Vec3,
+    pub color: Color,
+    pub intensity: f32,
+    /// Apparent radius of the light's disc, in radians.
+    pub angular_radius: f32,
+    pub samples: u32,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, color: Color, intensity: f32) -> Self {
+        DirectionalLight {
+            direction: direction.normalize(),
+            color,
+            intensity,
+            angular_radius: 0.0,
+            samples: 1,
+        }
+    }
+
+    pub fn with_disc(mut self, angular_radius: f32, samples: u32) -> Self {
+        self.angular_radius = angular_radius;
+        self.samples = samples;
+        self
+    }
+}
+
+impl LightSource for DirectionalLight {
+    fn sample(&self, _point: &Vec3, u: f32, v: f32, _pick: f32) -> LightSample {
+        let direction = if self.angular_radius > 0.0 {
+            let (tangent, bitangent) = tangent_frame(&self.direction);
+            let r = self.angular_radius.tan() * u.sqrt();
+            let phi = 2.0 * PI * v;
+            (self.direction + tangent * (r * phi.cos()) + bitangent * (r * phi.sin())).normalize()
+        } else {
+            self.direction
+        };
+        LightSample { direction, distance: f32::INFINITY, intensity: self.intensity }
+    }
+
+    fn color(&self) -> Color {
+        self.color
+    }
+
+    fn power(&self) -> f32 {
+        self.intensity
+    }
+
+    fn shadow_samples(&self) -> u32 {
+        if self.angular_radius > 0.0 { self.samples.max(1) } else { 1 }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AreaShape {
+    /// Parallelogram centered on `center`, spanned by the two edges.
+    Rect { center: Vec3, edge_u: Vec3, edge_v: Vec3 },
+    Disk { center: Vec3, normal: Vec3, radius: f32 },
+    Sphere { center: Vec3, radius: f32 },
+}
+
+/// A light with a surface. Each shadow sample treats one point of that surface
+/// as a small light of its own, so partially hidden lights give penumbrae
+/// instead of a razor-edged shadow. `intensity` is given off per unit of area,
+/// so a light seen edge-on or from far away lights less.
+pub struct AreaLight {
+    pub shape: AreaShape,
+    pub color: Color,
+    pub intensity: f32,
+    pub samples: u32,
+}
+
+impl AreaLight {
+    pub fn new(shape: AreaShape, color: Color, intensity: f32, samples: u32) -> Self {
+        AreaLight { shape, color, intensity, samples }
+    }
+
+    /// Area the samples are spread over; for a sphere, the half facing the point.
+    fn area(&self) -> f32 {
+        match self.shape {
+            AreaShape::Rect { edge_u, edge_v, .. } => edge_u.cross(&edge_v).magnitude(),
+            AreaShape::Disk { radius, .. } => PI * radius * radius,
+            AreaShape::Sphere { radius, .. } => 2.0 * PI * radius * radius,
+        }
+    }
+
+    /// A point on the surface and the surface's normal there.
+    fn point_on_surface(&self, point: &Vec3, u: f32, v: f32) -> (Vec3, Vec3) {
+        match self.shape {
+            AreaShape::Rect { center, edge_u, edge_v } => {
+                (center + edge_u * (u - 0.5) + edge_v * (v - 0.5), edge_u.cross(&edge_v).normalize())
+            }
+            AreaShape::Disk { center, normal, radius } => {
+                let normal = normal.normalize();
+                let (tangent, bitangent) = tangent_frame(&normal);
+                let r = radius * u.sqrt();
+                let phi = 2.0 * PI * v;
+                (center + tangent * (r * phi.cos()) + bitangent * (r * phi.sin()), normal)
+            }
+            AreaShape::Sphere { center, radius } => {
+                // Punto uniforme en la esfera, pasado a la mitad que mira hacia `point`
+                let z = 1.0 - 2.0 * u;
+                let r = (1.0 - z * z).max(0.0).sqrt();
+                let phi = 2.0 * PI * v;
+                let mut direction = Vec3::new(r * phi.cos(), r * phi.sin(), z);
+                if direction.dot(&(point - center)) < 0.0 {
+                    direction = -direction;
+                }
+                (center + direction * radius, direction)
+            }
+        }
+    }
+}
+
+impl LightSource for AreaLight {
+    fn sample(&self, point: &Vec3, u: f32, v: f32, _pick: f32) -> LightSample {
+        let (target, normal) = self.point_on_surface(point, u, v);
+        let (direction, distance) = toward(point, &target);
+        // Rectangulos y discos brillan por las dos caras; la esfera solo por fuera
+        let cos_light = match self.shape {
+            AreaShape::Sphere { .. } => (-direction.dot(&normal)).max(0.0),
+            _ => direction.dot(&normal).abs(),
+        };
+        let intensity = self.intensity * cos_light * self.area() * attenuation(distance, None);
+        LightSample { direction, distance, intensity }
+    }
+
+    fn color(&self) -> Color {
+        self.color
+    }
+
+    fn power(&self) -> f32 {
+        self.intensity * self.area()
+    }
+
+    fn shadow_samples(&self) -> u32 {
+        self.samples.max(1)
+    }
+}
+
+/// Emissive cubes that glow with the same color, lighting their surroundings.
+/// Every face glows with its cube's emission, so what reaches a point depends
+/// on how much of the cubes it sees and from how far: the same light a path
+/// would pick up by hitting them. Each sample picks one cube, the brighter and
+/// bigger ones more often, so a wall of glowing blocks costs no more shadow
+/// rays than a single one.
+#[derive(Clone)]
+pub struct EmitterLight {
+    pub cubes: Vec<Cube>,
+    pub samples: u32,
+    color: Color,
+    // Potencia acumulada de los cubos, normalizada para que el ultimo valga 1
+    cdf: Vec<f32>,
+    power: f32,
+}
+
+impl EmitterLight {
+    /// `cubes` should share `emission_color()`; the first one gives the color.
+    pub fn new(cubes: Vec<Cube>, samples: u32) -> Self {
+        let color = cubes.first().map(|cube| cube.material.emission_color()).unwrap_or(Color::new(0, 0, 0));
+        let mut total = 0.0;
+        let mut cdf: Vec<f32> = cubes
+            .iter()
+            .map(|cube| {
+                total += cube.material.emission_strength * cube.surface_area();
+                total
+            })
+            .collect();
+        if total > 0.0 {
+            cdf.iter_mut().for_each(|value| *value /= total);
+        }
+        EmitterLight { cubes, samples, color, cdf, power: total }
+    }
+}
+
+impl LightSource for EmitterLight {
+    fn sample(&self, point: &Vec3, u: f32, v: f32, pick: f32) -> LightSample {
+        let index = self.cdf.partition_point(|&value| value <= pick).min(self.cubes.len().saturating_sub(1));
+        let (Some(cube), Some(&high)) = (self.cubes.get(index), self.cdf.get(index)) else {
+            return LightSample { direction: Vec3::new(0.0, 1.0, 0.0), distance: 0.0, intensity: 0.0 };
+        };
+        let low = if index == 0 { 0.0 } else { self.cdf[index - 1] };
+        let probability = high - low;
+        if probability <= 0.0 {
+            return LightSample { direction: Vec3::new(0.0, 1.0, 0.0), distance: 0.0, intensity: 0.0 };
+        }
+        // Lo que sobra de `pick` dentro del intervalo del cubo es otro numero uniforme: elige la cara
+        let face_pick = ((pick - low) / probability).clamp(0.0, 0.999_999);
+        let (target, normal) = cube.sample_surface(face_pick, u, v);
+        let (direction, distance) = toward(point, &target);
+        let cos_light = -normal.dot(&direction);
+        if cos_light <= 0.0 {
+            return LightSample { direction, distance, intensity: 0.0 };
+        }
+        // Radiancia * area * coseno / distancia^2, y el 1 / PI del BRDF difuso,
+        // dividido por la probabilidad de haber elegido este cubo
+        let emitted = cube.material.emission_strength * cos_light * cube.surface_area() / (PI * probability);
+        // El rayo de sombra no debe chocar con la cara de la que salio la muestra
+        LightSample { direction, distance: distance - 1e-3, intensity: emitted * attenuation(distance, None) }
+    }
+
+    fn color(&self) -> Color {
+        self.color
+    }
+
+    fn power(&self) -> f32 {
+        self.power
+    }
+
+    fn shadow_samples(&self) -> u32 {
+        self.samples.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+
+    const WHITE: Color = Color { r: 255, g: 255, b: 255 };
+
+    #[test]
+    fn disk_light_integrates_to_its_analytic_irradiance() {
+        // Sobre el eje de un disco de radio R a altura h: integral de cos * cos' / d^2 = PI R^2 / (h^2 + R^2)
+        let (radius, height) = (1.0, 2.0);
+        let light = AreaLight::new(
+            AreaShape::Disk { center: Vec3::new(0.0, height, 0.0), normal: Vec3::new(0.0, -1.0, 0.0), radius },
+            WHITE,
+            3.0,
+            1,
+        );
+        let n = 64;
+        let mut total = 0.0;
+        for j in 0..n {
+            for i in 0..n {
+                let (u, v) = ((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                let sample = light.sample(&Vec3::zeros(), u, v, 0.0);
+                total += sample.intensity * sample.direction.y;
+            }
         }
+        let expected = 3.0 * PI * radius * radius / (height * height + radius * radius);
+        let mean = total / (n * n) as f32;
+        assert!((mean - expected).abs() < 0.01 * expected, "{} vs {}", mean, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn small_area_light_falls_off_like_a_point_light() {
+        let size = 0.01;
+        let rect = AreaShape::Rect {
+            center: Vec3::new(0.0, 0.0, -4.0),
+            edge_u: Vec3::new(size, 0.0, 0.0),
+            edge_v: Vec3::new(0.0, size, 0.0),
+        };
+        let light = AreaLight::new(rect, WHITE, 500.0, 1);
+        let point = Light::new(Vec3::new(0.0, 0.0, -4.0), WHITE, 500.0 * size * size);
+        let area = light.sample(&Vec3::zeros(), 0.5, 0.5, 0.0);
+        let expected = point.sample(&Vec3::zeros(), 0.5, 0.5, 0.0);
+        assert!((area.intensity - expected.intensity).abs() < 1e-3 * expected.intensity);
+        assert_eq!(light.power(), point.power());
+
+        // De canto no se ve
+        let edge_on = light.sample(&Vec3::new(3.0, 0.0, -4.0), 0.5, 0.5, 0.0);
+        assert!(edge_on.intensity.abs() < 1e-6);
+    }
+
+    #[test]
+    fn spot_cone_is_full_inside_and_dark_outside() {
+        let (inner, outer) = (0.3f32, 0.5f32);
+        let spot = SpotLight::new(Vec3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0), WHITE, 8.0, inner, outer);
+        // Puntos del suelo vistos desde la luz a distintos angulos del eje
+        let at_angle = |angle: f32| Vec3::new(2.0 * angle.tan(), 0.0, 0.0);
+        let falloff = |angle: f32| {
+            let sample = spot.sample(&at_angle(angle), 0.5, 0.5, 0.0);
+            sample.intensity * sample.distance * sample.distance / 8.0
+        };
+        assert!((falloff(0.0) - 1.0).abs() < 1e-5);
+        assert!((falloff(inner - 0.01) - 1.0).abs() < 1e-5);
+        assert_eq!(falloff(outer + 0.01), 0.0);
+        let between = [0.32, 0.36, 0.40, 0.44, 0.48].map(falloff);
+        assert!(between.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", between);
+    }
+
+    #[test]
+    fn range_window_fades_to_zero_at_the_range() {
+        let range = Some(10.0);
+        // Cerca de la luz el ventaneo casi no cambia la caida con el cuadrado de la distancia
+        assert!((attenuation(1.0, range) - 1.0).abs() < 1e-3);
+        assert!((attenuation(2.0, range) * 4.0 - 1.0).abs() < 0.01);
+        assert_eq!(attenuation(10.0, range), 0.0);
+        assert_eq!(attenuation(12.0, range), 0.0);
+        let fading = [6.0, 7.0, 8.0, 9.0, 9.9].map(|d| attenuation(d, range) * d * d);
+        assert!(fading.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", fading);
+        assert_eq!(attenuation(4.0, None), 1.0 / 16.0);
+        let light = Light::new(Vec3::zeros(), WHITE, 5.0).with_range(10.0);
+        assert_eq!(light.sample(&Vec3::new(0.0, 11.0, 0.0), 0.5, 0.5, 0.0).intensity, 0.0);
+    }
+
+    fn glowing_cube(min: Vec3, strength: f32) -> Cube {
+        let material = Material::black().with_emission(WHITE, strength);
+        Cube::new(min, min + Vec3::new(1.0, 1.0, 1.0), material)
+    }
+
+    #[test]
+    fn emitter_picks_cubes_in_proportion_to_their_power() {
+        let bright = glowing_cube(Vec3::new(-3.0, 0.0, 0.0), 3.0);
+        let dim = glowing_cube(Vec3::new(2.0, 0.0, 0.0), 1.0);
+        let light = EmitterLight::new(vec![bright, dim], 1);
+        assert_eq!(light.power(), 4.0 * 6.0);
+        // Tres cuartos de `pick` van al cubo brillante, a la izquierda
+        let point = Vec3::new(0.0, 0.5, 0.5);
+        for pick in [0.0, 0.3, 0.74] {
+            assert!(light.sample(&point, 0.5, 0.5, pick).direction.x < 0.0, "pick {}", pick);
+        }
+        for pick in [0.76, 0.9, 0.999] {
+            assert!(light.sample(&point, 0.5, 0.5, pick).direction.x > 0.0, "pick {}", pick);
+        }
+    }
+
+    #[test]
+    fn emitter_estimate_matches_its_cubes_sampled_one_by_one() {
+        let cubes = vec![
+            glowing_cube(Vec3::new(-3.0, 0.0, 0.0), 3.0),
+            glowing_cube(Vec3::new(2.0, 1.0, -1.0), 1.0),
+            glowing_cube(Vec3::new(0.0, 3.0, 0.0), 0.5),
+        ];
+        let point = Vec3::new(0.0, 0.0, 0.0);
+        let estimate = |light: &EmitterLight| {
+            let mut rng = fastrand::Rng::with_seed(7);
+            let n = 200_000;
+            let total: f32 = (0..n).map(|_| light.sample(&point, rng.f32(), rng.f32(), rng.f32()).intensity).sum();
+            total / n as f32
+        };
+        let together = estimate(&EmitterLight::new(cubes.clone(), 1));
+        let apart: f32 = cubes.into_iter().map(|cube| estimate(&EmitterLight::new(vec![cube], 1))).sum();
+        assert!((together - apart).abs() < 0.01 * apart, "{} vs {}", together, apart);
+    }
+}
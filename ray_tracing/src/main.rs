@@ -1,479 +1,118 @@
-mod framebuffer;
-mod color;
-mod texture;
-mod cube;
-mod rayintersect;
-mod material;
-mod camera;
-mod light;
-use std::f32::consts::PI;
-use camera::Camera;
-use cube::Cube;
-use material::Material;
-use light::Light;
-use framebuffer::FrameBuffer;
-use texture::Texture;
-use rayintersect::{RayIntersect, Intersect};
-use minifb::{Window, WindowOptions, Key};
-use nalgebra_glm::{Vec2, Vec3};
-use std::time::Duration;
-use color::Color;
-use once_cell::sync::Lazy;
-use std::sync::Arc;
-use rayon::prelude::*;
-
-const EPSILON: f32 = 1e-4;
-const ZOOM:f32 = 0.5;
-//Skybox (15 puntos)
-const SKYBOX_COLOR_NIGHT: (usize, usize, usize) = (4,12,36);
-const SKYBOX_COLOR_DAY: (usize, usize, usize) = (135, 206, 235);
-static STONE:Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/dirt.png")));
-static PYRAMID:Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/pyrstone.png")));
-static EMERALD:Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/emerald.png")));
-static WATER_TEXTURE:Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/water.png")));
-static RED_STONE_TEXTURE:Lazy<Arc<Texture>> = Lazy::new(|| Arc::new(Texture::new("assets/red.png")));
-
-fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
-    incident - 2.0 * incident.dot(normal) * normal
-}
-
-fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
-    let cosi = -incident.dot(normal).max(-1.0).min(1.0);
-    
-    let (n_cosi, eta, n_normal);
-
-    if cosi < 0.0 {
-        n_cosi = -cosi;
-        eta = 1.0 / eta_t;
-        n_normal = -normal;
-    } else {
-        n_cosi = cosi;
-        eta = eta_t;
-        n_normal = *normal;
-    }
-    let k = 1.0 - eta * eta * (1.0 - n_cosi * n_cosi);
-    
-    if k < 0.0 {
-        reflect(incident, &n_normal)
-    } else {
-        eta * incident + (eta * n_cosi - k.sqrt()) * n_normal
-    }
-}
-
-
-
-fn cast_shadow(
-    intersect: &Intersect,
-    light: &Light,
-    objects: &[Cube],
-) -> f32 {
-    let light_dir = (light.position - intersect.point).normalize();
-    let shadow_ray_origin = intersect.point + intersect.normal * EPSILON; // Offset to avoid acne
-    let mut shadow_intensity = 0.0;
-
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir);
-        if shadow_intersect.is_intersecting {
-            shadow_intensity = 1.0;
-            break;
+mod cli;
+#[cfg(feature = "viewer")]
+mod viewer;
+use cli::{Command, RenderOptions};
+use ray_tracing::{AdaptiveSampling, FrameBuffer, Renderer, Scene};
+use std::error::Error;
+use std::process::ExitCode;
+use std::time::Instant;
+
+fn render_to_file(options: &RenderOptions) -> Result<(), Box<dyn Error>> {
+    let mut scene = Scene::load(&options.scene)?;
+    if let Some(time) = options.time {
+        if scene.day_cycle.is_none() {
+            return Err(format!("{} has no `sun`, so --time has nothing to move", options.scene).into());
         }
+        scene.set_time_of_day(time);
     }
-
-    shadow_intensity
-}
-
-pub fn cast_ray(ray_origin: &Vec3, ray_direction: &Vec3, objects: &[Cube], lights: &[Light], depth:u32, is_day: bool) -> Color {
-    if depth > 3 {
-        return if is_day {Color::new(SKYBOX_COLOR_DAY.0 as u8, SKYBOX_COLOR_DAY.1 as u8, SKYBOX_COLOR_DAY.2 as u8)} else {Color::new(SKYBOX_COLOR_NIGHT.0 as u8, SKYBOX_COLOR_NIGHT.1 as u8, SKYBOX_COLOR_NIGHT.2 as u8)};
+    if options.night {
+        scene.set_day(false);
     }
-    //println!("Casting ray from origin: {:?}, direction: {:?}", ray_origin, ray_direction);
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY;
-    for object in objects {
-        let i = object.ray_intersect(ray_origin, ray_direction);
-        //println!("Intersect result: {:?}", i);
-        if i.is_intersecting && i.distance < zbuffer {
-            intersect = i;
-            zbuffer = intersect.distance;
-        }
-    }
-    if !intersect.is_intersecting {
-        //println!("No intersection. Returning background color.");
-        return if is_day {Color::new(SKYBOX_COLOR_DAY.0 as u8, SKYBOX_COLOR_DAY.1 as u8, SKYBOX_COLOR_DAY.2 as u8)} else {Color::new(SKYBOX_COLOR_NIGHT.0 as u8, SKYBOX_COLOR_NIGHT.1 as u8, SKYBOX_COLOR_NIGHT.2 as u8)};
+    if let Some(samples) = options.emitter_samples {
+        scene.set_emitter_samples(samples);
     }
-    let ambient_light_factor = if is_day { 5.0 } else { 0.05 };  //Ciclo de dia y noche (10 puntos)
-    let ambient_light = intersect.material.albedo[0] * ambient_light_factor;
-    let mut final_color = intersect.material.emission * intersect.material.emission_strength; 
-    final_color = final_color + Color::new(ambient_light as u8, ambient_light as u8, ambient_light as u8); 
-    //Soporte para diferentes luces (10 puntos)
-    for light in lights {
-        let light_dir = (light.position - intersect.point).normalize();
-        let view_dir = (ray_origin - intersect.point).normalize();
-        let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
-        
-
-        let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0);
-        let specular_intensity = view_dir
-            .dot(&reflect_dir)
-            .max(0.0)
-            .powf(intersect.material.specular);
 
-        let shadow = cast_shadow(&intersect, light, objects);
-        let diffuse = intersect.material.get_diffuse(intersect.u, intersect.v)
-            * diffuse_intensity
-            * light.intensity
-            * intersect.material.albedo[0]
-            * (1.0 - shadow);
-
-        let specular = light.color
-            * specular_intensity
-            * light.intensity
-            * intersect.material.albedo[1]
-            * (1.0 - shadow);
-        
-        
-        final_color = final_color + diffuse + specular;
+    let start = Instant::now();
+    let mut framebuffer = FrameBuffer::new(options.width, options.height);
+    framebuffer.display = scene.display;
+    if let Some(exposure) = options.exposure {
+        framebuffer.display.exposure = exposure;
     }
-
-    let mut reflect_color = Color::new(0, 0, 0);
-    let reflectivity = intersect.material.albedo[2];
-    if reflectivity > 0.0 {
-        let reflect_dir = reflect(&-ray_direction, &intersect.normal).normalize();
-        let reflect_origin = intersect.point + intersect.normal * EPSILON;
-        reflect_color = cast_ray(&reflect_origin, &reflect_dir, objects, lights, depth +1, is_day);
-
+    if let Some(tone_map) = options.tone_map {
+        framebuffer.display.tone_map = tone_map;
     }
-
-
-    let mut refract_color = Color::new(0,0,0);
-    let transparency = intersect.material.albedo[3];
-    if transparency > 0.0 {
-        let refract_dir = refract(&ray_direction, &intersect.normal, intersect.material.refractive_index);
-        let refract_origin = intersect.point - intersect.normal * EPSILON;
-        refract_color = cast_ray(&refract_origin, &refract_dir, objects, lights, depth +1, is_day);
+    let mut antialiasing = scene.antialiasing;
+    if let Some(grid) = options.aa_grid {
+        antialiasing.grid = grid;
     }
-    final_color * (1.0-reflectivity-transparency) + (reflect_color * reflectivity) + (refract_color * transparency)
-    
-}
-
-pub fn render(framebuffer: &mut FrameBuffer, objects: &Vec<Cube>, camera: &mut Camera, lights: &[Light], is_day:bool) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
-    let aspect_ratio = width / height;
-    let fov = PI/3.0;
-    let perspective_scale = (fov/2.0).tan();
-    //println!("Rendering... Camera position: {:?}", camera.eye);
-
-    for y in 0..framebuffer.height {
-        for x in 0..framebuffer.width {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_y = -(2.0 * y as f32) / height + 1.0;
-
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
-
-            let ray_direction = &Vec3::new(screen_x, screen_y, -1.0).normalize();
-            let rotated_direction = camera.basis_change(&ray_direction);
-
-            let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, lights, 0, is_day);
-            framebuffer.set_current_color(pixel_color);
-            framebuffer.point(x, y);
-        }
+    if let Some(pattern) = options.aa_pattern {
+        antialiasing.pattern = pattern;
     }
-    //println!("Finished rendering.");
-}
-
-
-pub fn render_parallel(
-    framebuffer: &mut FrameBuffer,
-    objects: &[Cube],
-    camera: &Camera,
-    lights: &[Light],
-    is_day: bool,
-) {
-    let width = framebuffer.width;
-    let height = framebuffer.height;
-    let aspect_ratio = width as f32 / height as f32;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov / 2.0).tan();
-
-    // Create a buffer to store colors computed in parallel
-    let colors: Vec<Color> = (0..width * height)
-        .into_par_iter()
-        .map(|i| {
-            let x = i % width;
-            let y = i / width;
-
-            let screen_x = (2.0 * x as f32) / width as f32 - 1.0;
-            let screen_y = -(2.0 * y as f32) / height as f32 + 1.0;
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
-            let screen_y = screen_y * perspective_scale;
-
-            let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
-            let rotated_direction = camera.basis_change(&ray_direction);
-
-            // Cast the ray and get the color for the current pixel
-            cast_ray(&camera.eye, &rotated_direction, objects, lights, 0, is_day)
-        })
-        .collect();
-
-    // Write colors to framebuffer
-    for (i, color) in colors.into_iter().enumerate() {
-        let x = i % width;
-        let y = i / width;
-        framebuffer.set_current_color(color);
-        framebuffer.point(x, y);
+    if let Some(filter) = options.filter {
+        antialiasing.filter = filter;
     }
-}
-
-
-
-//mode: se refiera a si queremos crear la cuadrícula sobre los ejex xy o yz
-//grid_size: se refiera al tamaño que se quiere para la cuadrícula
-pub fn create_grid(initial_min_position: &mut Vec3, initial_max_position: &mut Vec3, cube_length: f32, mode:usize, grid_size: usize, material:Material ) -> Vec<Cube>{
-    let mut material_grid: Vec<Cube> = Vec::new();
-    let mut current_min = *initial_min_position; // Dereference to get the actual value
-    let mut current_max = *initial_max_position;
-    let vertical_sum_vector = match mode {
-        1 => Vec3::new(cube_length, 0.0, 0.0), //Cuando queremos usar la cuadrícula en xy
-        2 => Vec3::new(cube_length, 0.0, 0.0),
-        _ => Vec3::new(0.0, 0.0, 0.0) //Caso base
-    };
-
-    let horizontal_sum_vector = match mode {
-        1 => Vec3::new(0.0, cube_length, 0.0), //Cuando queremos usar la cuadrícula en xy
-        2 => Vec3::new(0.0, 0.0, cube_length),
-        _ => Vec3::new(0.0, 0.0, 0.0) //Caso base
-    };
-
-    for i in 0..grid_size + 1 {
-
-        for j in 0..grid_size +1 {
-            material_grid.push(Cube { min: current_min, 
-                max:current_max, 
-                material: material.clone()});
-
-            current_max += vertical_sum_vector;
-            current_min += vertical_sum_vector;
+    let mut adaptive = scene.adaptive;
+    if options.adaptive {
+        let settings = adaptive.get_or_insert_with(AdaptiveSampling::default);
+        if let Some(threshold) = options.threshold {
+            settings.threshold = threshold;
+        }
+        if let Some(max_samples) = options.max_samples {
+            settings.max_samples = max_samples;
+        }
+        if let Some(time_budget) = options.time_budget {
+            settings.time_budget = Some(time_budget);
         }
-        *initial_min_position -= horizontal_sum_vector;
-        *initial_max_position -= horizontal_sum_vector;
-        current_min = *initial_min_position;
-        current_max = * initial_max_position;
-        
-
-
     }
-
-    material_grid
-}
-
-pub fn create_empty_grid(
-    initial_min_position: &mut Vec3,
-    initial_max_position: &mut Vec3,
-    cube_length: f32,
-    mode: usize,
-    grid_size: usize,
-    material: Material,
-) -> Vec<Cube> {
-    let mut material_grid: Vec<Cube> = Vec::new();
-    let mut current_min = *initial_min_position; // Dereference to get the actual value
-    let mut current_max = *initial_max_position;
-
-    let vertical_sum_vector = match mode {
-        1 => Vec3::new(cube_length, 0.0, 0.0), // For XY grid
-        2 => Vec3::new(cube_length, 0.0, 0.0), // For XZ grid
-        _ => Vec3::new(0.0, 0.0, 0.0),         // Base case
-    };
-
-    let horizontal_sum_vector = match mode {
-        1 => Vec3::new(0.0, cube_length, 0.0), // For XY grid
-        2 => Vec3::new(0.0, 0.0, cube_length), // For XZ grid
-        _ => Vec3::new(0.0, 0.0, 0.0),         // Base case
+    let renderer = Renderer {
+        integrator: options.integrator,
+        samples: options.samples,
+        antialiasing,
+        adaptive,
+        ..Renderer::default()
     };
+    renderer.render(&mut framebuffer, &scene);
+    let render_time = start.elapsed();
+    let total_samples: u64 = framebuffer.sample_counts.iter().map(|&count| count as u64).sum();
+
+    framebuffer
+        .save(&options.output)
+        .map_err(|error| format!("could not write {}: {}", options.output.display(), error))?;
+    eprintln!(
+        "wrote {} ({}x{}) in {:?}, {:.1} samples per pixel",
+        options.output.display(),
+        options.width,
+        options.height,
+        render_time,
+        total_samples as f64 / framebuffer.sample_counts.len() as f64
+    );
 
-    for i in 0..=grid_size {
-        for j in 0..=grid_size {
-            // Only add cubes if they are on the grid's boundaries
-            if i == 0 || j == 0 || i == grid_size || j == grid_size {
-                material_grid.push(Cube {
-                    min: current_min,
-                    max: current_max,
-                    material: material.clone(),
-                });
-            }
-
-            current_max += vertical_sum_vector;
-            current_min += vertical_sum_vector;
-        }
-
-        // Move to the next row or column in the grid
-        *initial_min_position -= horizontal_sum_vector;
-        *initial_max_position -= horizontal_sum_vector;
-        current_min = *initial_min_position;
-        current_max = *initial_max_position;
+    if let Some(path) = &options.heatmap {
+        framebuffer
+            .save_sample_heatmap(path)
+            .map_err(|error| format!("could not write {}: {}", path.display(), error))?;
+        eprintln!("wrote sample heatmap to {}", path.display());
     }
-
-    material_grid
+    Ok(())
 }
 
+#[cfg(feature = "viewer")]
+use viewer::view;
 
+#[cfg(not(feature = "viewer"))]
+fn view(_scene_path: &str) -> Result<(), Box<dyn Error>> {
+    Err("this binary was built without the `viewer` feature; use `ray_tracing render` instead".into())
+}
 
-
-// Function to parallelize the initial ray casting for each pixel
-
-fn main() {
-    let window_height = 600;
-    let window_width = 800;
-
-    let framebuffer_height = 600;
-    let framebuffer_width = 800;
-
-
-    let mut DIRT = Material::material_with_texture(Color::new(128,128,128), 2.0, [0.9, 0.1, 0.0, 0.0], Some(STONE.clone()), 1.0, Color::new(0,0,0), 0.0);
-    let mut STONEP = Material::material_with_texture(Color::new(128,128,128), 2.0, [0.9, 0.1, 0.0, 0.0], Some(PYRAMID.clone()), 1.0, Color::new(0,0,0), 0.0);
-    let mut EMERALDM = Material::material_with_texture(Color::new(37, 150, 190), 7.0, [0.4, 0.6, 0.0, 0.0], Some(EMERALD.clone()), 1.0, Color::new(37, 150, 190), 5.0);
-    let mut WATER = Material::material_with_texture(Color::new(0, 0, 255), 2.0, [0.9, 0.1, 0.4, 0.5], Some(WATER_TEXTURE.clone()), 1.33, Color::new(0, 0, 0), 0.0);
-    let mut RUBY = Material::material_with_texture(Color::new(0, 0, 0), 7.0, [0.4, 0.6, 0.0, 0.0], Some(WATER_TEXTURE.clone()), 1.0, Color::new(255, 0, 0), 10.0);
-    let frame_delay = Duration::from_millis(0);
-    let mut is_day = true;
-    let mut camera = Camera::new(
-        Vec3::new(-5.0, 5.0, -5.0), // Move the camera backward
-        Vec3::new(0.0, 0.0, 0.0), //original: -0.5, -0.5, -1.0
-        Vec3::new(0.0, 1.0, 0.0),
-        false
-    );
-    
-    let mut test_world = create_empty_grid(&mut Vec3::new(-0.5, -0.5, -0.5),
-    &mut Vec3::new(0.5, 0.5, 0.5),
-      1.0, 2, 10, DIRT.clone());
-
-
-    let test_world2 = create_empty_grid(&mut Vec3::new(0.5, 0.5, -1.5),
-&mut Vec3::new(1.5, 1.5, -0.5), 1.0, 2, 8, STONEP.clone());
-
-    let test_world3 = create_empty_grid(&mut Vec3::new(1.5, 1.5, -2.5),
-    &mut Vec3::new(2.5, 2.5, -1.5), 1.0, 2, 6, STONEP.clone());
-
-    let test_world4 = create_empty_grid(&mut Vec3::new(2.5, 2.5, -3.5),
-    &mut Vec3::new(3.5, 3.5, -2.5), 1.0, 2, 4, STONEP.clone());
-
-    let test_world6 = create_empty_grid(&mut Vec3::new(10.5, -0.5, -0.5),
-    &mut Vec3::new(11.5, 0.5, 0.5), 1.0, 2, 10, STONEP.clone());
-
-    let test_world7 = create_grid(&mut Vec3::new(11.5, -0.5, -0.5),
-    &mut Vec3::new(12.5, 0.5, 0.5), 1.0, 2, 9, WATER.clone());
-
-    let test_world5 =create_empty_grid(&mut Vec3::new(3.5, 3.5, -4.5),
-    &mut Vec3::new(4.5, 4.5, -3.5), 1.0, 2, 2, STONEP.clone());
-
-    
-    let objects = vec![
-        Cube {
-            min: Vec3::new(4.5, 4.5, -4.5), 
-            max: Vec3::new(5.5, 5.5, -5.5),
-            material: EMERALDM.clone()},
-        Cube {
-                min: Vec3::new(10.5, 0.5, -0.5), 
-                max: Vec3::new(11.5, 1.5, 0.5),
-                material: RUBY.clone()},
-        Cube {
-            min: Vec3::new(10.5, 0.5, -9.5), 
-            max: Vec3::new(11.5, 1.5, -10.5),
-            material: RUBY.clone()},
-            Cube {
-                min: Vec3::new(20.5, 0.5, -0.5), 
-                max: Vec3::new(21.5, 1.5, 0.5),
-                material: RUBY.clone()},
-                Cube {
-                    min: Vec3::new(20.5, 0.5, -9.5), 
-                    max: Vec3::new(21.5, 1.5, -10.5),
-                    material: RUBY.clone()}
-
-    ];
-    test_world.extend(test_world2);
-    test_world.extend(test_world3);
-    test_world.extend(test_world4);
-    test_world.extend(test_world5);
-    test_world.extend(test_world6);
-    test_world.extend(test_world7);
-    test_world.extend(objects);
-
-    
-
-    let mut framebuffer = FrameBuffer::new(framebuffer_width, framebuffer_height);
-    framebuffer.set_background_color(Color::new(128,128,128));
-
-    let rotation_speed = PI/60.0;
-
-    let mut lights = [
-        Light::new(Vec3::new(7.0, 5.0, 0.0), Color::new(255, 255, 255), 2.0),
-        Light::new(Vec3::new(7.0, 45.0, 6.0), Color::new(255, 255, 255), 2.0),
-        //Light::new(Vec3::new(-3.0, 5.0, 5.0), Color::new(255, 255, 255), 10.0),
-    ];
-    render_parallel(&mut framebuffer, &test_world, &mut camera, &lights, is_day);
-    let mut window = Window::new(
-        "Minecraft RayTracer",
-        window_width,
-        window_height,
-        WindowOptions::default(),
-    ).unwrap();
-    
-    while window.is_open() {
-        if window.is_key_down(Key::Escape){
-            break;
-        }
-        if window.is_key_down(Key::Left){
-            camera.orbit(rotation_speed, 0.0);
-        }
-        if window.is_key_down(Key::Right){
-            camera.orbit(-rotation_speed, 0.0);
-        }
-        if window.is_key_down(Key::Up){
-            camera.orbit(0.0, -rotation_speed);
-        }
-        if window.is_key_down(Key::Down){
-            camera.orbit(0.0, rotation_speed);
-        }
-        if window.is_key_down(Key::W){
-            camera.zoom(ZOOM);
-        }
-        if window.is_key_down(Key::S){
-            camera.zoom(-ZOOM);
-        }
-        if window.is_key_down(Key::N){
-            is_day = !is_day;
-            if is_day {
-                lights = [
-                    Light::new(Vec3::new(7.0, 5.0, 0.0), Color::new(255, 255, 255), 2.0),
-                    Light::new(Vec3::new(7.0, 45.0, 6.0), Color::new(255, 255, 255), 2.0),
-                    ];
-
-            }
-            else {
-                lights = [
-                Light::new(Vec3::new(7.0, 5.0, 0.0), Color::new(255, 255, 255), 1.0),
-                Light::new(Vec3::new(7.0, 45.0, 6.0), Color::new(255, 255, 255), 0.0),
-                //Light::new(Vec3::new(-3.0, 5.0, 5.0), Color::new(255, 255, 255), 10.0),
-            ];
-
-            }
-            
-            camera.has_changed = true;
+fn main() -> ExitCode {
+    let result = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Command::View { scene }) => view(&scene),
+        Ok(Command::Render(options)) => render_to_file(&options),
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            Ok(())
         }
-
-        if window.is_key_down(Key::D){
-            camera.move_center(Vec3::new(1.1, 1.0, 1.0));
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, cli::USAGE);
+            return ExitCode::FAILURE;
         }
+    };
 
-        if camera.has_changed {
-            render_parallel(&mut framebuffer, &test_world, &mut camera, &lights, is_day);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
         }
-        
-
-        window
-            .update_with_buffer(&framebuffer.cast_buffer(), framebuffer_width, framebuffer_height)
-            .unwrap();
-        std::thread::sleep(frame_delay);
     }
 }
@@ -1,17 +1,123 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
 use crate::color::Color;
-use once_cell::sync::Lazy;
+use crate::display::srgb_encode;
+use crate::spectrum::Spectrum;
 use std::sync::Arc;
-use crate::texture::Texture;
+use crate::brdf;
+use crate::cube::Face;
+use crate::sampler::Sampler;
+use crate::texture::{Texture, TextureRegion};
 
+/// Metallic-roughness material, shaded with the GGX BRDF in `brdf`.
 #[derive(Clone)]
 pub struct Material {
+  /// Base color: what dielectrics scatter diffusely and the tint of a metal's
+  /// reflections. The texture replaces it when there is one.
   pub diffuse: Color,
-  pub specular: f32,
-  pub albedo: [f32;4],
   pub texture: Option<Arc<Texture>>,
+  /// Multiplies the base color, texture included. The old diffuse weight.
+  pub base_weight: f32,
+  /// Part of `texture` in use, one tile when it is an atlas.
+  pub region: TextureRegion,
+  /// Texture for each block face, in `Face::ALL` order. A face without one
+  /// keeps `texture` and `region`.
+  pub faces: Option<Arc<[Option<TextureSlot>; 6]>>,
+  /// How `texture` is filtered and wrapped.
+  pub sampler: Sampler,
+  /// 0.0 for dielectrics (stone, water, plastic), 1.0 for metals.
+  pub metallic: f32,
+  /// 0.0 is a perfect mirror, 1.0 fully blurry reflections.
+  pub roughness: f32,
   pub refractive_index: f32,
+  /// Share of the light that goes through a dielectric instead of scattering.
+  pub transmission: f32,
   pub emission: Color,           // Materiales emisivos (15 puntos)
-  pub emission_strength: f32
+  pub emission_strength: f32,
+  /// Beer-Lambert density for light crossing a transparent material, per unit
+  /// of distance. Channels the diffuse color lacks are absorbed the fastest.
+  pub absorption: f32,
+  /// Tangent-space normals (OpenGL layout, green pointing up the image),
+  /// loaded with `Texture::load_linear`.
+  pub normal_map: Option<TextureSlot>,
+  /// LabPBR-style specular map, also linear: red is smoothness and green
+  /// above 230/255 marks metal. Overrides `roughness` and `metallic`.
+  pub specular_map: Option<TextureSlot>,
+  /// Emitted color per texel, scaled by `emission_strength`.
+  pub emission_map: Option<TextureSlot>,
+  /// What the alpha channel of `texture` does.
+  pub alpha_mode: AlphaMode
+}
+
+/// How a material uses the alpha channel of its texture.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+pub enum AlphaMode {
+  /// Alpha is ignored.
+  #[default]
+  Opaque,
+  /// Texels with alpha below `cutoff` are holes: every ray, shadow rays too,
+  /// goes through them. For leaves, flowers and glass panes.
+  Mask { cutoff: f32 },
+  /// The surface only covers `alpha` of what is behind it.
+  Blend
+}
+
+/// A texture, or a tile of an atlas: what a block face or a map reads from.
+#[derive(Clone)]
+pub struct TextureSlot {
+  pub texture: Arc<Texture>,
+  pub region: TextureRegion,
+  /// For maps: laid out like the material's atlas, so the map follows the
+  /// tile of each block face instead of keeping `region`.
+  pub shares_layout: bool
+}
+
+impl TextureSlot {
+  /// The whole of `texture`.
+  pub fn whole(texture: Arc<Texture>) -> Self {
+    TextureSlot { texture, region: TextureRegion::FULL, shares_layout: false }
+  }
+}
+
+// Las texturas se comparan por identidad: dos cargas del mismo archivo son distintas
+impl PartialEq for TextureSlot {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.texture, &other.texture) && self.region == other.region && self.shares_layout == other.shares_layout
+  }
+}
+
+// Valor del canal verde desde el que LabPBR considera metal al texel
+const METAL_THRESHOLD: f32 = 230.0 / 255.0;
+
+/// PBR parameters for a Phong material, from `from_phong`. Phong materials are
+/// all dielectrics: their reflections were never tinted by the diffuse color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhongConversion {
+  pub base_weight: f32,
+  pub roughness: f32,
+  pub refractive_index: f32,
+  pub transmission: f32
+}
+
+/// PBR parameters for the old Phong ones: the specular exponent, the
+/// (diffuse, specular, reflect, transmit) weights and the refractive index.
+/// Diffuse weight scales the base color and transmit weight becomes
+/// transmission. On opaque materials the reflect weight becomes the head-on
+/// reflectance, through the refractive index that gives it, and lowers the
+/// roughness the exponent maps to.
+pub fn from_phong(specular: f32, albedo: [f32; 4], refractive_index: f32) -> PhongConversion {
+  let transmission = albedo[3].clamp(0.0, 1.0);
+  // Blinn-Phong n ~ GGX alpha = sqrt(2 / (n + 2)), y roughness = sqrt(alpha)
+  let phong_roughness = (2.0 / (specular.max(0.0) + 2.0)).powf(0.25);
+  let roughness = phong_roughness * (1.0 - albedo[2] - albedo[3]).clamp(0.0, 1.0);
+  // En los transparentes el indice es real y Fresnel ya decide cuanto se refleja
+  let refractive_index = if transmission > 0.0 {
+    refractive_index
+  } else {
+    let f0 = brdf::dielectric_f0(refractive_index).max(albedo[2].clamp(0.0, 0.99));
+    brdf::ior_for_f0(f0)
+  };
+  PhongConversion { base_weight: albedo[0].clamp(0.0, 1.0), roughness, refractive_index, transmission }
 }
 
 impl Material {
@@ -20,21 +126,14 @@ impl Material {
     specular: f32,
     albedo: [f32; 4],
     refractive_index: f32,
-    emission: Color,           
+    emission: Color,
     emission_strength: f32
   ) -> Self {
-    Material {
-      diffuse,
-      specular,
-      albedo,
-      texture:None,
-      refractive_index,
-      emission,
-      emission_strength
-    }
+    Material::material_with_texture(diffuse, specular, albedo, None, refractive_index, emission, emission_strength)
   }
 
-  pub fn material_with_texture( 
+  /// Material from the old Phong parameters, converted with `from_phong`.
+  pub fn material_with_texture(
     diffuse: Color,
     specular: f32,
     albedo: [f32; 4],
@@ -42,38 +141,262 @@ impl Material {
     refractive_index: f32,
     emission: Color,           // New: emission color
     emission_strength: f32 ) -> Self {
+      let phong = from_phong(specular, albedo, refractive_index);
+      Material::pbr(diffuse, 0.0, phong.roughness, phong.refractive_index, phong.transmission)
+        .with_base_weight(phong.base_weight)
+        .with_texture(texture)
+        .with_emission(emission, emission_strength)
+    }
+
+    pub fn pbr(base_color: Color, metallic: f32, roughness: f32, refractive_index: f32, transmission: f32) -> Self {
       Material {
-        diffuse,
-        specular,
-        albedo,
-        texture,
+        diffuse: base_color,
+        texture: None,
+        base_weight: 1.0,
+        region: TextureRegion::FULL,
+        faces: None,
+        sampler: Sampler::default(),
+        metallic: metallic.clamp(0.0, 1.0),
+        roughness: roughness.clamp(0.0, 1.0),
         refractive_index,
-        emission,
-        emission_strength
+        transmission: transmission.clamp(0.0, 1.0),
+        emission: Color::new(0, 0, 0),
+        emission_strength: 0.0,
+        absorption: 0.0,
+        normal_map: None,
+        specular_map: None,
+        emission_map: None,
+        alpha_mode: AlphaMode::Opaque
       }
     }
 
+    pub fn with_texture(mut self, texture: Option<Arc<Texture>>) -> Self {
+      self.texture = texture;
+      self
+    }
+
+    pub fn with_base_weight(mut self, base_weight: f32) -> Self {
+      self.base_weight = base_weight;
+      self
+    }
+
+    pub fn with_region(mut self, region: TextureRegion) -> Self {
+      self.region = region;
+      self
+    }
+
+    /// Per-face textures, in `Face::ALL` order. Ignored by shapes other than
+    /// cubes and voxels.
+    pub fn with_faces(mut self, faces: Option<[Option<TextureSlot>; 6]>) -> Self {
+      self.faces = faces.map(Arc::new);
+      self
+    }
+
+    /// This material as seen on the block face `normal` points out of, with
+    /// that face's texture in place of `texture`.
+    pub fn on_face(&self, normal: Vec3) -> Material {
+      let mut material = self.clone();
+      let face = self.faces.as_ref().zip(Face::from_normal(normal)).and_then(|(faces, face)| faces[face as usize].clone());
+      if let Some(face) = face {
+        for map in [&mut material.normal_map, &mut material.specular_map, &mut material.emission_map].into_iter().flatten() {
+          if map.shares_layout {
+            map.region = face.region;
+          }
+        }
+        material.texture = Some(face.texture);
+        material.region = face.region;
+      }
+      material
+    }
 
-    pub fn get_diffuse(&self, u: f32, v: f32) -> Color {
-      if let Some(texture) = &self.texture {
-          let x = ((u * texture.width as f32).round() as u32).min(texture.width - 1);
-          let y = ((v * texture.height as f32).round() as u32).min(texture.height - 1);
-          texture.get_pixel_color(x, y)
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+      self.alpha_mode = alpha_mode;
+      self
+    }
+
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+      self.sampler = sampler;
+      self
+    }
+
+    pub fn with_emission(mut self, emission: Color, strength: f32) -> Self {
+      self.emission = emission;
+      self.emission_strength = strength;
+      self
+    }
+
+    pub fn with_absorption(mut self, absorption: f32) -> Self {
+      self.absorption = absorption;
+      self
+    }
+
+    pub fn with_normal_map(mut self, normal_map: Option<TextureSlot>) -> Self {
+      self.normal_map = normal_map;
+      self
+    }
+
+    pub fn with_specular_map(mut self, specular_map: Option<TextureSlot>) -> Self {
+      self.specular_map = specular_map;
+      self
+    }
+
+    pub fn with_emission_map(mut self, emission_map: Option<TextureSlot>) -> Self {
+      self.emission_map = emission_map;
+      self
+    }
+
+    /// Linear base color at (u, v), from the texture if there is one.
+    /// `footprint` is the size of the pixel there in UV units, see `Texture::sample`.
+    pub fn get_diffuse(&self, u: f32, v: f32, footprint: f32) -> Spectrum {
+      let base = if let Some(texture) = &self.texture {
+          texture.sample_region(&self.sampler, self.region, u, v, footprint)
       } else {
-          self.diffuse
+          Spectrum::from_srgb(self.diffuse)
+      };
+      base * self.base_weight
+  }
+
+  /// Whether (u, v) is a hole of an `AlphaMode::Mask` material. Read from the
+  /// full-size texture, so the holes are the same for every ray.
+  pub fn is_cut_out(&self, u: f32, v: f32) -> bool {
+      match (&self.texture, self.alpha_mode) {
+          (Some(texture), AlphaMode::Mask { cutoff }) => texture.sample_alpha(&self.sampler, self.region, u, v, 0.0) < cutoff,
+          _ => false
+      }
+  }
+
+  /// How much of what is behind (u, v) the surface hides: below 1.0 only
+  /// for `AlphaMode::Blend`. Mask holes are skipped before shading.
+  pub fn coverage(&self, u: f32, v: f32, footprint: f32) -> f32 {
+      match (&self.texture, self.alpha_mode) {
+          (Some(texture), AlphaMode::Blend) => texture.sample_alpha(&self.sampler, self.region, u, v, footprint),
+          _ => 1.0
+      }
+  }
+
+  /// Light given off at (u, v), from the emission map if there is one.
+  pub fn emitted(&self, u: f32, v: f32, footprint: f32) -> Spectrum {
+      if self.emission_strength <= 0.0 {
+          return Spectrum::black();
+      }
+      match &self.emission_map {
+          Some(map) => map.texture.sample_region(&self.sampler, map.region, u, v, footprint) * self.emission_strength,
+          None => Spectrum::from_srgb(self.emission) * self.emission_strength
+      }
+  }
+
+  /// Average emitted color, the mean of the emission map if there is one.
+  /// What an emissive cube gives off when it is sampled as a light.
+  pub fn emission_color(&self) -> Color {
+      match &self.emission_map {
+          // Una huella de 1 cubre toda la region: el ultimo nivel de mip es su promedio
+          Some(map) => map.texture.sample_region(&self.sampler, map.region, 0.5, 0.5, 1.0).map(srgb_encode).to_color(),
+          None => self.emission
+      }
+  }
+
+  /// Replaces `roughness` and `metallic` with the specular map texel at (u, v).
+  pub fn apply_specular_map(&mut self, u: f32, v: f32, footprint: f32) {
+      if let Some(map) = &self.specular_map {
+          let texel = map.texture.sample_region(&self.sampler, map.region, u, v, footprint);
+          self.roughness = (1.0 - texel.r).clamp(0.0, 1.0);
+          self.metallic = if texel.g >= METAL_THRESHOLD { 1.0 } else { 0.0 };
+      }
+  }
+
+  /// Shading normal at (u, v): `normal` tilted by the normal map, read in the
+  /// frame where `tangent` and `bitangent` are the directions u and v grow in.
+  /// Without a map or a frame the normal comes back as it is.
+  pub fn shading_normal(&self, normal: &Vec3, tangent: &Vec3, bitangent: &Vec3, u: f32, v: f32, footprint: f32) -> Vec3 {
+      let map = match &self.normal_map {
+          Some(map) if tangent.norm_squared() > 0.0 => map,
+          _ => return *normal,
+      };
+      let texel = map.texture.sample_region(&self.sampler, map.region, u, v, footprint);
+      // Gram-Schmidt por si el marco no es del todo ortogonal a la normal
+      let t = (tangent - normal * normal.dot(tangent)).normalize();
+      let b = normal.cross(&t);
+      // v crece hacia abajo en la imagen, y el verde apunta hacia arriba
+      let b = if b.dot(bitangent) > 0.0 { -b } else { b };
+      let perturbed = t * (2.0 * texel.r - 1.0) + b * (2.0 * texel.g - 1.0) + normal * (2.0 * texel.b - 1.0);
+      if perturbed.norm_squared() < 1e-8 {
+          return *normal;
+      }
+      perturbed.normalize()
+  }
+
+  /// Fraction of light a shadow ray keeps after crossing `distance` inside the material.
+  pub fn absorb(&self, diffuse: Spectrum, distance: f32) -> Spectrum {
+      if self.absorption <= 0.0 {
+          return Spectrum::gray(1.0);
       }
+      diffuse.map(|c| (-self.absorption * (1.0 - c.clamp(0.0, 1.0)) * distance).exp())
   }
 
 
   pub fn black() -> Self {
-    Material {
-      diffuse: Color::new(0, 0, 0),
-      specular: 0.0,
-      albedo: [0.0, 0.0, 0.0, 0.0],
-      texture: None,
-      refractive_index: 0.0,
-      emission: Color::new(0, 0, 0),
-      emission_strength: 0.0
-    }
+    Material::pbr(Color::new(0, 0, 0), 0.0, 1.0, 0.0, 0.0)
   }
-}
\ No newline at end of file
+}
+
+/// Same parameters and the very same textures, so the two look identical.
+impl PartialEq for Material {
+  fn eq(&self, other: &Self) -> bool {
+    let same_texture = match (&self.texture, &other.texture) {
+      (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+      (None, None) => true,
+      _ => false
+    };
+    same_texture
+      && self.diffuse == other.diffuse
+      && self.base_weight == other.base_weight
+      && self.region == other.region
+      && self.faces == other.faces
+      && self.sampler == other.sampler
+      && self.metallic == other.metallic
+      && self.roughness == other.roughness
+      && self.refractive_index == other.refractive_index
+      && self.transmission == other.transmission
+      && self.emission == other.emission
+      && self.emission_strength == other.emission_strength
+      && self.absorption == other.absorption
+      && self.normal_map == other.normal_map
+      && self.specular_map == other.specular_map
+      && self.emission_map == other.emission_map
+      && self.alpha_mode == other.alpha_mode
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn phong_exponents_map_to_roughness() {
+    // Cuanto mas alto el exponente, mas pulido; con exponente 0 queda del todo rugoso
+    let roughness = [0.0, 10.0, 50.0, 500.0].map(|n| from_phong(n, [0.9, 0.1, 0.0, 0.0], 1.0).roughness);
+    assert!((roughness[0] - 1.0).abs() < 1e-6);
+    assert!(roughness.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", roughness);
+    // alpha = roughness^2 = sqrt(2 / (n + 2))
+    assert!((roughness[1].powi(4) - 2.0 / 12.0).abs() < 1e-5);
+  }
+
+  #[test]
+  fn phong_weights_become_reflectance_and_transmission() {
+    let mirror = from_phong(50.0, [0.3, 0.1, 0.6, 0.0], 1.0);
+    assert_eq!(mirror.base_weight, 0.3);
+    assert_eq!(mirror.transmission, 0.0);
+    // El peso de reflejo pasa a ser la reflectancia de frente, y pule la superficie
+    assert!((brdf::dielectric_f0(mirror.refractive_index) - 0.6).abs() < 1e-4);
+    assert!(mirror.roughness < from_phong(50.0, [0.3, 0.1, 0.0, 0.0], 1.0).roughness);
+    // Un reflejo menor que el de un dielectrico comun no lo baja
+    let matte = from_phong(10.0, [0.9, 0.1, 0.01, 0.0], 1.5);
+    assert!((brdf::dielectric_f0(matte.refractive_index) - 0.04).abs() < 1e-4);
+
+    // En los transparentes se respeta el indice: Fresnel decide cuanto se refleja
+    let glass = from_phong(125.0, [0.0, 0.5, 0.1, 0.8], 1.5);
+    assert_eq!((glass.transmission, glass.refractive_index), (0.8, 1.5));
+    assert!(glass.roughness < 0.2);
+    assert_eq!(from_phong(10.0, [1.4, 0.0, 0.0, 1.3], 1.5).base_weight, 1.0);
+  }
+}
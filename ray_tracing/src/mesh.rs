@@ -0,0 +1,41 @@
+use nalgebra_glm::Vec3;
+use crate::bvh::{Aabb, Bvh};
+use crate::rayintersect::{RayIntersect, Intersect};
+use crate::triangle::Triangle;
+
+/// A triangle soup with its own BVH, so a dense model is a single object in the
+/// scene BVH and only its own triangles are visited once a ray reaches it.
+pub struct Mesh {
+    triangles: Vec<Triangle>,
+    bvh: Bvh,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let bvh = Bvh::new(&triangles);
+        Mesh { triangles, bvh }
+    }
+
+    /// Scales the mesh around the origin and then moves it by `translation`.
+    pub fn transform(&mut self, scale: f32, translation: Vec3) {
+        for triangle in &mut self.triangles {
+            for vertex in &mut triangle.vertices {
+                vertex.position = vertex.position * scale + translation;
+                if scale < 0.0 {
+                    vertex.normal = -vertex.normal;
+                }
+            }
+        }
+        self.bvh = Bvh::new(&self.triangles);
+    }
+}
+
+impl RayIntersect for Mesh {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        self.bvh.intersect(&self.triangles, ray_origin, ray_direction)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounds()
+    }
+}
@@ -0,0 +1,211 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use crate::color::Color;
+use crate::material::Material;
+use crate::mesh::Mesh;
+use crate::texture::Texture;
+use crate::triangle::{Triangle, Vertex};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Obj(tobj::LoadError),
+    Mtl(tobj::LoadError),
+    Texture(String, image::ImageError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Obj(error) => write!(f, "could not load OBJ: {}", error),
+            ObjError::Mtl(error) => write!(f, "could not load MTL: {}", error),
+            ObjError::Texture(path, error) => write!(f, "could not load texture {}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Loads every model in an OBJ file (plus its MTL library) into a single mesh.
+/// Faces are triangulated; texture paths in the MTL are relative to the OBJ file.
+pub fn load_obj(path: &str) -> Result<Mesh, ObjError> {
+    let options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ..Default::default()
+    };
+    let (models, materials) = tobj::load_obj(path, &options).map_err(ObjError::Obj)?;
+    let materials = materials.map_err(ObjError::Mtl)?;
+
+    let base_dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let mut textures: HashMap<String, Arc<Texture>> = HashMap::new();
+    let materials = materials
+        .iter()
+        .map(|mtl| convert_material(mtl, base_dir, &mut textures).map(Arc::new))
+        .collect::<Result<Vec<_>, _>>()?;
+    let default_material = Arc::new(Material::new(Color::new(200, 200, 200), 2.0, [0.9, 0.1, 0.0, 0.0], 1.0, Color::new(0, 0, 0), 0.0));
+
+    let mut triangles = Vec::new();
+    for model in &models {
+        let mesh = &model.mesh;
+        let material = mesh
+            .material_id
+            .and_then(|id| materials.get(id).cloned())
+            .unwrap_or_else(|| default_material.clone());
+
+        let position = |i: usize| Vec3::new(mesh.positions[3 * i], mesh.positions[3 * i + 1], mesh.positions[3 * i + 2]);
+        for face in mesh.indices.chunks_exact(3) {
+            let ids = [face[0] as usize, face[1] as usize, face[2] as usize];
+            let positions = ids.map(position);
+            let face_normal = (positions[1] - positions[0]).cross(&(positions[2] - positions[0])).normalize();
+
+            let vertices = [0, 1, 2].map(|k| {
+                let i = ids[k];
+                let normal = if mesh.normals.is_empty() {
+                    face_normal
+                } else {
+                    Vec3::new(mesh.normals[3 * i], mesh.normals[3 * i + 1], mesh.normals[3 * i + 2]).normalize()
+                };
+                // En OBJ la v crece hacia arriba, en nuestras texturas hacia abajo
+                let uv = if mesh.texcoords.is_empty() {
+                    Vec2::new(0.0, 0.0)
+                } else {
+                    Vec2::new(mesh.texcoords[2 * i], 1.0 - mesh.texcoords[2 * i + 1])
+                };
+                Vertex::new(positions[k], normal, uv)
+            });
+            triangles.push(Triangle::new(vertices, material.clone()));
+        }
+    }
+
+    Ok(Mesh::new(triangles))
+}
+
+fn to_color(rgb: [f32; 3]) -> Color {
+    Color::new(
+        (rgb[0] * 255.0).clamp(0.0, 255.0) as u8,
+        (rgb[1] * 255.0).clamp(0.0, 255.0) as u8,
+        (rgb[2] * 255.0).clamp(0.0, 255.0) as u8,
+    )
+}
+
+fn parse_rgb(value: &str) -> Option<[f32; 3]> {
+    let mut parts = value.split_whitespace().map(|part| part.parse::<f32>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => Some([r, g, b]),
+        (Some(Ok(gray)), None, None) => Some([gray, gray, gray]),
+        _ => None,
+    }
+}
+
+/// Maps MTL fields onto our material: Kd -> diffuse, Ns -> specular exponent,
+/// Ks -> specular weight, map_Kd -> texture, Ni -> refractive index,
+/// d/Tr -> transparency, Ke -> emission, and illum 3/5/7 turn on reflections.
+/// Those go through the Phong conversion; the PBR extension's Pm (metallic)
+/// and Pr (roughness) replace what it gives when present.
+fn convert_material(
+    mtl: &tobj::Material,
+    base_dir: &Path,
+    textures: &mut HashMap<String, Arc<Texture>>,
+) -> Result<Material, ObjError> {
+    let diffuse = to_color(mtl.diffuse.unwrap_or([0.8, 0.8, 0.8]));
+    let specular = mtl.shininess.unwrap_or(2.0).max(1.0);
+    let specular_weight = mtl.specular.map(|ks| (ks[0] + ks[1] + ks[2]) / 3.0).unwrap_or(0.1);
+
+    let transparency = match (mtl.dissolve, mtl.unknown_param.get("Tr")) {
+        (Some(d), _) => 1.0 - d,
+        (None, Some(tr)) => tr.trim().parse::<f32>().unwrap_or(0.0),
+        (None, None) => 0.0,
+    }
+    .clamp(0.0, 1.0);
+    let reflectivity = match mtl.illumination_model {
+        Some(3) | Some(5) | Some(7) => specular_weight,
+        _ => 0.0,
+    };
+    let albedo = [1.0 - specular_weight, specular_weight, reflectivity, transparency];
+
+    // Ke puede pasar de 1.0; guardamos el color normalizado y el exceso como intensidad
+    let (emission, emission_strength) = match mtl.unknown_param.get("Ke").and_then(|ke| parse_rgb(ke)) {
+        Some(ke) => {
+            let peak = ke[0].max(ke[1]).max(ke[2]);
+            if peak > 0.0 {
+                (to_color([ke[0] / peak, ke[1] / peak, ke[2] / peak]), peak)
+            } else {
+                (Color::new(0, 0, 0), 0.0)
+            }
+        }
+        None => (Color::new(0, 0, 0), 0.0),
+    };
+
+    let texture = match &mtl.diffuse_texture {
+        Some(name) => {
+            let path = base_dir.join(name).to_string_lossy().into_owned();
+            let texture = match textures.get(&path) {
+                Some(texture) => texture.clone(),
+                None => {
+                    let texture = Arc::new(Texture::load(&path).map_err(|error| ObjError::Texture(path.clone(), error))?);
+                    textures.insert(path, texture.clone());
+                    texture
+                }
+            };
+            Some(texture)
+        }
+        None => None,
+    };
+
+    let mut material = Material::material_with_texture(
+        diffuse,
+        specular,
+        albedo,
+        texture,
+        mtl.optical_density.unwrap_or(1.0),
+        emission,
+        emission_strength,
+    );
+    let param = |name: &str| mtl.unknown_param.get(name).and_then(|value| value.trim().parse::<f32>().ok());
+    if let Some(metallic) = param("Pm") {
+        material.metallic = metallic.clamp(0.0, 1.0);
+    }
+    if let Some(roughness) = param("Pr") {
+        material.roughness = roughness.clamp(0.0, 1.0);
+    }
+    Ok(material)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rayintersect::RayIntersect;
+    use std::fs;
+
+    #[test]
+    fn loads_faces_and_mtl_fields() {
+        let dir = std::env::temp_dir().join(format!("ray_tracing_obj_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("quad.mtl"), "newmtl lamp\nKd 1.0 0.5 0.0\nKe 2.0 1.0 0.0\nd 0.75\nPm 0.7\n").unwrap();
+        // Un cuadrado en z = 0 que tobj parte en dos triangulos
+        fs::write(
+            dir.join("quad.obj"),
+            "mtllib quad.mtl\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nusemtl lamp\nf 1/1 2/2 3/3 4/4\n",
+        )
+        .unwrap();
+        let mesh = load_obj(dir.join("quad.obj").to_str().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        let mesh = mesh.unwrap();
+
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+        for (x, y) in [(0.8, 0.1), (0.1, 0.8)] {
+            let hit = mesh.ray_intersect(&Vec3::new(x, y, 1.0), &direction);
+            assert!(hit.is_intersecting, "({}, {}) misses", x, y);
+            // v se invierte: en OBJ crece hacia arriba y en la imagen hacia abajo
+            assert!((hit.u - x).abs() < 1e-5 && (hit.v - (1.0 - y)).abs() < 1e-5, "uv ({}, {})", hit.u, hit.v);
+            assert_eq!(hit.material.diffuse, Color::new(255, 127, 0));
+            assert_eq!((hit.material.emission, hit.material.emission_strength), (Color::new(255, 127, 0), 2.0));
+            assert_eq!(hit.material.transmission, 0.25);
+            assert_eq!(hit.material.metallic, 0.7);
+        }
+        assert!(!mesh.ray_intersect(&Vec3::new(1.5, 0.5, 1.0), &direction).is_intersecting);
+    }
+}
@@ -0,0 +1,65 @@
+use nalgebra_glm::Vec3;
+use crate::bvh::Aabb;
+use crate::material::Material;
+use crate::rayintersect::{RayIntersect, Intersect};
+
+/// Infinite plane. The texture repeats once per world unit along the plane.
+pub struct Plane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: Material,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, material: Material) -> Self {
+        Plane { point, normal: normal.normalize(), material }
+    }
+}
+
+/// Two unit vectors that, together with `normal`, form an orthonormal basis.
+pub fn tangent_frame(normal: &Vec3) -> (Vec3, Vec3) {
+    let helper = if normal.y.abs() < 0.999 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+/// Distance along the ray to the plane through `point`, if it is in front of the origin.
+pub fn plane_distance(ray_origin: &Vec3, ray_direction: &Vec3, point: &Vec3, normal: &Vec3) -> Option<f32> {
+    let denom = normal.dot(ray_direction);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let distance = (point - ray_origin).dot(normal) / denom;
+    if distance < 0.0 {
+        None
+    } else {
+        Some(distance)
+    }
+}
+
+impl RayIntersect for Plane {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let distance = match plane_distance(ray_origin, ray_direction, &self.point, &self.normal) {
+            Some(distance) => distance,
+            None => return Intersect::empty(),
+        };
+
+        let hit_point = ray_origin + ray_direction * distance;
+        let (tangent, bitangent) = tangent_frame(&self.normal);
+        let local = hit_point - self.point;
+        let u = local.dot(&tangent);
+        let v = local.dot(&bitangent);
+        Intersect::new(hit_point, self.normal, distance, self.material.clone(), u, v)
+            .with_tangents(tangent, bitangent)
+            .thin()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
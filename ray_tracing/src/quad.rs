@@ -0,0 +1,54 @@
+use nalgebra_glm::Vec3;
+use crate::bvh::Aabb;
+use crate::material::Material;
+use crate::plane::plane_distance;
+use crate::rayintersect::{RayIntersect, Intersect};
+
+/// Flat parallelogram spanned by `edge_u` and `edge_v` from `corner`. Useful for
+/// sprites: the texture covers the whole quad with (0, 0) at `corner`.
+pub struct Quad {
+    pub corner: Vec3,
+    pub edge_u: Vec3,
+    pub edge_v: Vec3,
+    pub material: Material,
+    normal: Vec3,
+}
+
+impl Quad {
+    pub fn new(corner: Vec3, edge_u: Vec3, edge_v: Vec3, material: Material) -> Self {
+        let normal = edge_u.cross(&edge_v).normalize();
+        Quad { corner, edge_u, edge_v, material, normal }
+    }
+}
+
+impl RayIntersect for Quad {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let distance = match plane_distance(ray_origin, ray_direction, &self.corner, &self.normal) {
+            Some(distance) => distance,
+            None => return Intersect::empty(),
+        };
+
+        // Coordenadas del punto en la base (edge_u, edge_v)
+        let hit_point = ray_origin + ray_direction * distance;
+        let local = hit_point - self.corner;
+        let n = self.edge_u.cross(&self.edge_v);
+        let w = n / n.dot(&n);
+        let u = w.dot(&local.cross(&self.edge_v));
+        let v = w.dot(&self.edge_u.cross(&local));
+
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return Intersect::empty();
+        }
+
+        Intersect::new(hit_point, self.normal, distance, self.material.clone(), u, v)
+            .with_tangents(self.edge_u.normalize(), self.edge_v.normalize())
+            .thin()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let padding = Vec3::new(1e-4, 1e-4, 1e-4);
+        let bounds = Aabb::new(self.corner, self.corner + self.edge_u + self.edge_v)
+            .union(&Aabb::new(self.corner + self.edge_u, self.corner + self.edge_v));
+        Some(Aabb::new(bounds.min - padding, bounds.max + padding))
+    }
+}
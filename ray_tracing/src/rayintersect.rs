@@ -1,6 +1,6 @@
 use nalgebra_glm::Vec3;
-use crate::color::Color;
 use crate::material::Material;
+use crate::bvh::Aabb;
 
 #[derive(Clone)]
 #[allow(dead_code)]
@@ -11,7 +11,16 @@ pub struct Intersect {
     pub is_intersecting: bool,
     pub material: Material,
     pub u: f32,
-    pub v: f32
+    pub v: f32,
+    /// Directions in which `u` and `v` grow at the hit, for normal maps.
+    /// Zero for shapes without a texture frame.
+    pub tangent: Vec3,
+    pub bitangent: Vec3,
+    /// Hit on a surface with no inside (planes, quads, disks, triangles).
+    /// Light crosses it as a sheet, with nothing to absorb on the way.
+    pub thin: bool,
+    /// Hit on an emissive cube, which the scene samples as an `EmitterLight`.
+    pub emitter: bool
 }
 
 impl Intersect {
@@ -23,32 +32,59 @@ impl Intersect {
             is_intersecting: true,
             material,
             u,
-            v
-
+            v,
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
+            thin: false,
+            emitter: false
         }
     }
 
+    pub fn thin(mut self) -> Self {
+        self.thin = true;
+        self
+    }
+
+    pub fn emitter(mut self, emitter: bool) -> Self {
+        self.emitter = emitter;
+        self
+    }
+
+    pub fn with_tangents(mut self, tangent: Vec3, bitangent: Vec3) -> Self {
+        self.tangent = tangent;
+        self.bitangent = bitangent;
+        self
+    }
+
     pub fn empty() -> Self {
         Intersect {
             point: Vec3::zeros(),
             normal: Vec3::zeros(),
             distance: 0.0,
             is_intersecting: false,
-            material: Material{
-              diffuse: Color::new(0, 0, 0),
-              specular: 0.0,
-              albedo: [0.0, 0.0, 0.0, 0.0],
-              texture:None,
-              refractive_index: 0.0,
-            emission: Color::new(0, 0, 0),
-            emission_strength: 0.0
-            },
+            material: Material::black(),
             u: 0.0,
-            v: 0.0
+            v: 0.0,
+            tangent: Vec3::zeros(),
+            bitangent: Vec3::zeros(),
+            thin: false,
+            emitter: false
         }
     }
 }
 
 pub trait RayIntersect {
   fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect;
+  /// `None` for unbounded objects such as infinite planes.
+  fn bounding_box(&self) -> Option<Aabb>;
+}
+
+impl<T: RayIntersect + ?Sized> RayIntersect for Box<T> {
+  fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+    (**self).ray_intersect(ray_origin, ray_direction)
+  }
+
+  fn bounding_box(&self) -> Option<Aabb> {
+    (**self).bounding_box()
+  }
 }
\ No newline at end of file
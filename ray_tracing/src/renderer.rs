@@ -0,0 +1,804 @@
+use fastrand::Rng;
+use nalgebra_glm::Vec3;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Instant;
+use crate::adaptive::{AdaptiveSampling, PixelEstimate};
+use crate::brdf;
+use crate::filter::AntiAliasing;
+use crate::framebuffer::FrameBuffer;
+use crate::light::LightSample;
+use crate::material::Material;
+use crate::plane::tangent_frame;
+use crate::scene::Scene;
+use crate::spectrum::Spectrum;
+
+const EPSILON: f32 = 1e-4;
+
+pub fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3 {
+    incident - 2.0 * incident.dot(normal) * normal
+}
+
+pub fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
+    let cosi = -incident.dot(normal).clamp(-1.0, 1.0);
+    
+    let (n_cosi, eta, n_normal);
+
+    if cosi < 0.0 {
+        n_cosi = -cosi;
+        eta = 1.0 / eta_t;
+        n_normal = -normal;
+    } else {
+        n_cosi = cosi;
+        eta = eta_t;
+        n_normal = *normal;
+    }
+    let k = 1.0 - eta * eta * (1.0 - n_cosi * n_cosi);
+    
+    if k < 0.0 {
+        reflect(incident, &n_normal)
+    } else {
+        eta * incident + (eta * n_cosi - k.sqrt()) * n_normal
+    }
+}
+
+/// Exact Fresnel reflectance of an unpolarized ray hitting a dielectric with
+/// index `eta_t`, from either side of the surface. 1.0 under total internal
+/// reflection.
+pub fn fresnel(incident: &Vec3, normal: &Vec3, eta_t: f32) -> f32 {
+    let cosi = incident.dot(normal).clamp(-1.0, 1.0);
+    // Si el rayo sale del medio se invierten los indices
+    let (eta_i, eta_t) = if cosi > 0.0 { (eta_t, 1.0) } else { (1.0, eta_t) };
+    let sint = eta_i / eta_t * (1.0 - cosi * cosi).max(0.0).sqrt();
+    if sint >= 1.0 {
+        return 1.0;
+    }
+    let cost = (1.0 - sint * sint).max(0.0).sqrt();
+    let cosi = cosi.abs();
+    let parallel = (eta_t * cosi - eta_i * cost) / (eta_t * cosi + eta_i * cost);
+    let perpendicular = (eta_i * cosi - eta_t * cost) / (eta_i * cosi + eta_t * cost);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+// Superficies transparentes que un rayo de sombra puede cruzar antes de rendirse
+const MAX_SHADOW_CROSSINGS: u32 = 16;
+
+/// How much of the light in `sample` reaches `point`: white when nothing is in
+/// the way, black behind an opaque object. Transparent materials let their
+/// `transmission` of the light through, tinted by the hue of their diffuse color, each time the
+/// ray crosses into a closed one, and absorb it between that entry and the
+/// matching exit if they have an `absorption`. Open surfaces such as glass
+/// panes are crossed as sheets: tinted from either side, never absorbed.
+fn shadow_transmittance(
+    point: &Vec3,
+    normal: &Vec3,
+    sample: &LightSample,
+    scene: &Scene,
+) -> Spectrum {
+    let mut origin = point + normal * EPSILON; // Offset to avoid acne
+    if !scene.occluded(&origin, &sample.direction, sample.distance) {
+        return Spectrum::gray(1.0);
+    }
+
+    let mut transmittance = Spectrum::gray(1.0);
+    let mut remaining = sample.distance;
+    let mut travelled = 0.0;
+    // Donde se entro al medio cerrado en el que va el rayo, si se vio la entrada
+    let mut medium_start: Option<f32> = None;
+    // Si el rayo acaba de salir de un medio; en una piscina de bloques se sale
+    // de un cubo justo donde entra al siguiente, y eso no es una superficie real
+    let mut just_left = false;
+    for _ in 0..MAX_SHADOW_CROSSINGS {
+        let hit = scene.intersect(&origin, &sample.direction);
+        if !hit.is_intersecting || hit.distance >= remaining {
+            return transmittance;
+        }
+        let transparency = brdf::transmission(&hit.material);
+        let coverage = hit.material.coverage(hit.u, hit.v, 0.0);
+        if transparency <= 0.0 && coverage >= 1.0 {
+            return Spectrum::black();
+        }
+
+        let diffuse = hit.material.get_diffuse(hit.u, hit.v, 0.0);
+        // Solo el tono del color: lo que deja pasar lo decide `transparency`,
+        // y lo que la textura no cubre pasa sin filtrar
+        let tint = diffuse / diffuse.max_component().max(1e-4);
+        let crossing = Spectrum::gray(1.0 - coverage) + tint * (transparency * coverage);
+        let entering = hit.normal.dot(&sample.direction) < 0.0;
+        let position = travelled + hit.distance;
+        if transparency <= 0.0 {
+            // Lamina con AlphaMode::Blend: de cualquier lado pasa lo que no cubre
+            transmittance *= 1.0 - coverage;
+        } else if hit.thin {
+            // Superficie abierta: se cruza como una lamina, de cualquier lado y sin adentro
+            transmittance *= crossing;
+        } else if entering {
+            if !(just_left && hit.distance < 2.0 * EPSILON) {
+                transmittance *= crossing;
+            }
+            medium_start = Some(position);
+        } else {
+            // Una salida sin entrada quiere decir que el rayo empezo adentro del medio
+            let start = match medium_start {
+                Some(start) => start,
+                None => {
+                    transmittance *= crossing;
+                    0.0
+                }
+            };
+            transmittance *= hit.material.absorb(diffuse, position - start);
+            medium_start = None;
+        }
+        just_left = !entering && transparency > 0.0 && !hit.thin;
+        if transmittance.max_component() <= 0.0 {
+            return Spectrum::black();
+        }
+
+        remaining -= hit.distance + EPSILON;
+        travelled = position + EPSILON;
+        origin = hit.point + sample.direction * EPSILON;
+    }
+    Spectrum::black()
+}
+
+/// Deterministic random numbers for a shading point, so the Whitted tracer
+/// stays reproducible while still jittering its light samples.
+fn point_rng(point: &Vec3) -> Rng {
+    let seed = (point.x.to_bits() as u64) ^ ((point.y.to_bits() as u64) << 21) ^ ((point.z.to_bits() as u64) << 42);
+    Rng::with_seed(seed)
+}
+
+/// GGX/Cook-Torrance reflection of every light in the scene, filtered by the
+/// light's color. Lights with a surface are sampled `shadow_samples` times on
+/// a jittered grid (random beyond the largest square), and the fraction of unblocked shadow rays gives soft
+/// shadows; `max_shadow_samples`, when set, caps that count for every light.
+/// `specular` is forwarded to `brdf::evaluate`.
+/// Both integrators use it, so a light gives the same direct illumination in each:
+/// a white diffuse surface facing a white light of intensity 1.0 reflects about 1.0.
+#[allow(clippy::too_many_arguments)]
+fn direct_lighting(
+    point: &Vec3,
+    normal: &Vec3,
+    material: &Material,
+    base_color: Spectrum,
+    view_dir: &Vec3,
+    scene: &Scene,
+    rng: &mut Rng,
+    specular: bool,
+    max_shadow_samples: Option<u32>,
+) -> Spectrum {
+    let mut color = Spectrum::black();
+    for (light, strength) in scene.lights() {
+        let light_color = Spectrum::from(light.color()) * strength;
+        let samples = max_shadow_samples.map_or(light.shadow_samples(), |cap| cap.min(light.shadow_samples())).max(1);
+        // Solo la parte que llena un cuadrado completo va en la rejilla; el resto
+        // es aleatorio, si no quedarian celdas sin muestrear
+        let grid = (samples as f32).sqrt().floor() as u32;
+        let mut light_contribution = Spectrum::black();
+
+        for i in 0..samples {
+            let (u, v) = if i < grid * grid {
+                (((i % grid) as f32 + rng.f32()) / grid as f32, ((i / grid) as f32 + rng.f32()) / grid as f32)
+            } else {
+                (rng.f32(), rng.f32())
+            };
+            let sample = light.sample(point, u, v, rng.f32());
+            if sample.intensity <= 0.0 {
+                continue;
+            }
+            let reflected = brdf::evaluate(material, base_color, normal, view_dir, &sample.direction, specular);
+            if reflected.is_black() {
+                continue;
+            }
+
+            let transmittance = shadow_transmittance(point, normal, &sample, scene);
+            if transmittance.is_black() {
+                continue;
+            }
+            light_contribution += reflected * light_color * transmittance * sample.intensity;
+        }
+        color += light_contribution / samples as f32;
+    }
+    color
+}
+
+// Por encima de esta rugosidad los reflejos se aproximan con la luz del cielo en vez de trazarlos
+const TRACED_ROUGHNESS: f32 = 0.6;
+
+/// Mirror direction of `direction`, or for rough surfaces the mirror direction
+/// about a random GGX microfacet, which blurs the reflection.
+fn glossy_reflection(direction: &Vec3, normal: &Vec3, roughness: f32, rng: &mut Rng) -> Vec3 {
+    let mirror = reflect(direction, normal).normalize();
+    if roughness < brdf::MIRROR_ROUGHNESS {
+        return mirror;
+    }
+    let half = brdf::sample_microfacet(normal, brdf::alpha(roughness), rng.f32(), rng.f32());
+    let reflected = reflect(direction, &half).normalize();
+    if reflected.dot(normal) > 0.0 { reflected } else { mirror }
+}
+
+/// How wide a ray is, for picking texture mip levels: a camera ray covers one
+/// pixel, and that footprint grows with the distance travelled.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RayCone {
+    /// Width at the ray's origin, in world units.
+    pub width: f32,
+    /// Growth in width per unit of distance; the angle of one pixel for camera rays.
+    pub spread: f32,
+}
+
+// Tope para el alargamiento de la huella en angulos rasantes
+const MAX_GRAZING_STRETCH: f32 = 4.0;
+// Apertura, en radianes, que se le da al cono tras un rebote difuso
+const DIFFUSE_SPREAD: f32 = 1.0;
+
+impl RayCone {
+    /// The cone after travelling `distance`.
+    pub fn advance(self, distance: f32) -> RayCone {
+        RayCone { width: self.width + self.spread * distance, ..self }
+    }
+
+    /// The cone after a bounce that scatters rays over a lobe about `lobe`
+    /// radians wide, so what a diffuse or glossy bounce hits reads coarser mips.
+    pub fn scatter(self, lobe: f32) -> RayCone {
+        RayCone { spread: self.spread + lobe, ..self }
+    }
+
+    /// Footprint on a surface hit at `distance`, seen at `cos_theta` from its
+    /// normal. UV units are taken to be world units, as on the unit cubes.
+    pub fn footprint(&self, distance: f32, cos_theta: f32) -> f32 {
+        let stretch = (1.0 / cos_theta.abs().max(1e-4)).min(MAX_GRAZING_STRETCH);
+        self.advance(distance).width * stretch
+    }
+}
+
+// Capas con AlphaMode::Blend que un rayo puede atravesar; las siguientes se tratan como opacas
+const MAX_BLEND_LAYERS: u32 = 16;
+
+/// Radiance arriving at `ray_origin` from `ray_direction`, in linear RGB.
+/// Whitted-style: direct light, an ambient term and up to three bounces of
+/// perfect reflection and refraction. `max_shadow_samples` goes to `direct_lighting`.
+pub fn cast_ray(ray_origin: &Vec3, ray_direction: &Vec3, scene: &Scene, depth:u32, cone: RayCone, max_shadow_samples: Option<u32>) -> Spectrum {
+    cast_ray_layered(ray_origin, ray_direction, scene, depth, 0, cone, max_shadow_samples)
+}
+
+/// `cast_ray` after looking through `layers` blended surfaces, which don't
+/// count as bounces.
+fn cast_ray_layered(ray_origin: &Vec3, ray_direction: &Vec3, scene: &Scene, depth: u32, layers: u32, cone: RayCone, max_shadow_samples: Option<u32>) -> Spectrum {
+    let sky = scene.sky_color();
+    if depth > 3 {
+        return sky;
+    }
+    let mut intersect = scene.intersect(ray_origin, ray_direction);
+    if !intersect.is_intersecting {
+        return sky;
+    }
+    let footprint = cone.footprint(intersect.distance, intersect.normal.dot(ray_direction));
+    intersect.material.apply_specular_map(intersect.u, intersect.v, footprint);
+    let material = &intersect.material;
+    // Con AlphaMode::Blend se mezcla con lo que hay detras
+    let coverage = if layers < MAX_BLEND_LAYERS { material.coverage(intersect.u, intersect.v, footprint) } else { 1.0 };
+    let behind = if coverage < 1.0 {
+        let behind_origin = offset_origin(&intersect.point, &intersect.normal, ray_direction);
+        cast_ray_layered(&behind_origin, ray_direction, scene, depth, layers + 1, cone.advance(intersect.distance), max_shadow_samples)
+    } else {
+        Spectrum::black()
+    };
+    if coverage <= 0.0 {
+        return behind;
+    }
+    let base_color = material.get_diffuse(intersect.u, intersect.v, footprint);
+    let cone = cone.advance(intersect.distance);
+    let shading_normal = material.shading_normal(&intersect.normal, &intersect.tangent, &intersect.bitangent, intersect.u, intersect.v, footprint);
+    // Normal del lado por el que llega el rayo, para planos y quads vistos por detras
+    let normal = if intersect.normal.dot(ray_direction) > 0.0 { -shading_normal } else { shading_normal };
+    let view_dir = (ray_origin - intersect.point).normalize();
+    let mut rng = point_rng(&intersect.point);
+
+    let transmission = brdf::transmission(material);
+    let opaque = 1.0 - transmission;
+    let fresnel_view = brdf::fresnel_schlick(brdf::specular_color(material, base_color), normal.dot(&view_dir));
+
+    let mut final_color = material.emitted(intersect.u, intersect.v, footprint);
+    // Luz ambiental: una fraccion del color del cielo, teñida por las luces de la escena
+    let ambient_light = scene.ambient_light();
+    final_color += base_color * (Spectrum::gray(1.0) - fresnel_view) * ambient_light * ((1.0 - material.metallic) * opaque);
+    final_color += direct_lighting(&intersect.point, &normal, material, base_color, &view_dir, scene, &mut rng, true, max_shadow_samples);
+
+    // La parte transparente es una interfaz dielectrica: Fresnel exacto, con reflexion total interna
+    let reflectance = if transmission > 0.0 { fresnel(ray_direction, &intersect.normal, material.refractive_index) } else { 0.0 };
+    let reflect_weight = fresnel_view * opaque + Spectrum::gray(transmission * reflectance);
+    if material.roughness < TRACED_ROUGHNESS {
+        let reflect_dir = glossy_reflection(ray_direction, &normal, material.roughness, &mut rng);
+        let reflect_origin = offset_origin(&intersect.point, &intersect.normal, &reflect_dir);
+        let reflect_color = cast_ray_layered(&reflect_origin, &reflect_dir, scene, depth +1, layers, cone, max_shadow_samples);
+        final_color += reflect_color * reflect_weight;
+    } else {
+        final_color += ambient_light * reflect_weight;
+    }
+
+    let refract_weight = transmission * (1.0 - reflectance);
+    if refract_weight > 0.0 {
+        let refract_dir = refract(ray_direction, &intersect.normal, material.refractive_index);
+        let refract_origin = offset_origin(&intersect.point, &intersect.normal, &refract_dir);
+        let refract_color = cast_ray_layered(&refract_origin, &refract_dir, scene, depth +1, layers, cone, max_shadow_samples);
+        final_color += refract_color * refract_weight;
+    }
+    final_color * coverage + behind * (1.0 - coverage)
+}
+
+// Limite duro de rebotes; la ruleta rusa casi siempre corta antes
+const MAX_PATH_DEPTH: u32 = 64;
+// Rebotes que siempre se hacen antes de empezar con la ruleta rusa
+const ROULETTE_START: u32 = 3;
+
+/// Moves `point` off the surface, to the side `direction` is heading.
+fn offset_origin(point: &Vec3, normal: &Vec3, direction: &Vec3) -> Vec3 {
+    if direction.dot(normal) >= 0.0 {
+        point + normal * EPSILON
+    } else {
+        point - normal * EPSILON
+    }
+}
+
+/// Direction around `normal` with probability density `cos(theta) / PI`.
+fn cosine_sample_hemisphere(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let (tangent, bitangent) = tangent_frame(normal);
+    let r = rng.f32().sqrt();
+    let phi = 2.0 * PI * rng.f32();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z).normalize()
+}
+
+/// Unbiased path-traced estimate of the radiance arriving at `ray_origin` from
+/// `ray_direction`. Every hit gathers direct light from the scene's lights,
+/// emissive cubes included, then picks one lobe at random: the dielectric
+/// interface of transparent materials (reflection or refraction by Fresnel),
+/// the GGX specular lobe or the diffuse one. Paths end by Russian roulette.
+/// `max_shadow_samples` goes to `direct_lighting`.
+pub fn trace_path(ray_origin: &Vec3, ray_direction: &Vec3, scene: &Scene, rng: &mut Rng, cone: RayCone, max_shadow_samples: Option<u32>) -> Spectrum {
+    let sky = scene.sky_color();
+    let mut radiance = Spectrum::black();
+    let mut throughput = Spectrum::gray(1.0);
+    let mut origin = *ray_origin;
+    let mut direction = *ray_direction;
+    let mut cone = cone;
+    // Si el rebote anterior ya conto la luz de los cubos emisivos con NEE
+    let mut after_diffuse = false;
+
+    for bounce in 0..MAX_PATH_DEPTH {
+        let mut intersect = scene.intersect(&origin, &direction);
+        if !intersect.is_intersecting {
+            radiance += throughput * sky;
+            break;
+        }
+
+        let footprint = cone.footprint(intersect.distance, intersect.normal.dot(&direction));
+        intersect.material.apply_specular_map(intersect.u, intersect.v, footprint);
+        let material = &intersect.material;
+        // Con AlphaMode::Blend el camino atraviesa la superficie con probabilidad 1 - alpha
+        let coverage = material.coverage(intersect.u, intersect.v, footprint);
+        if coverage < 1.0 && rng.f32() >= coverage {
+            cone = cone.advance(intersect.distance);
+            origin = offset_origin(&intersect.point, &intersect.normal, &direction);
+            continue;
+        }
+        let emitted = material.emitted(intersect.u, intersect.v, footprint);
+        let counted_by_nee = after_diffuse && intersect.emitter && !scene.emitters.is_empty();
+        if !emitted.is_black() && !counted_by_nee {
+            radiance += throughput * emitted;
+        }
+
+        let shading_normal = material.shading_normal(&intersect.normal, &intersect.tangent, &intersect.bitangent, intersect.u, intersect.v, footprint);
+        // Normal del lado por el que llega el rayo, para planos y quads vistos por detras
+        let normal = if intersect.normal.dot(&direction) > 0.0 { -shading_normal } else { shading_normal };
+        let view = -direction;
+        let base_color = material.get_diffuse(intersect.u, intersect.v, footprint);
+        cone = cone.advance(intersect.distance);
+        let transmission = brdf::transmission(material);
+        // Un espejo perfecto no puede muestrear luces; su reflejo se encuentra al rebotar
+        let glossy = material.roughness >= brdf::MIRROR_ROUGHNESS;
+        if transmission < 1.0 {
+            radiance += throughput * direct_lighting(&intersect.point, &normal, material, base_color, &view, scene, rng, glossy, max_shadow_samples);
+        }
+
+        if rng.f32() < transmission {
+            let reflectance = fresnel(&direction, &intersect.normal, material.refractive_index);
+            direction = if rng.f32() < reflectance {
+                reflect(&direction, &intersect.normal).normalize()
+            } else {
+                refract(&direction, &intersect.normal, material.refractive_index).normalize()
+            };
+            after_diffuse = false;
+        } else {
+            let n_dot_v = normal.dot(&view).max(1e-4);
+            let specular_color = brdf::specular_color(material, base_color);
+            let fresnel_view = brdf::fresnel_schlick(specular_color, n_dot_v);
+            let diffuse_albedo = base_color * (Spectrum::gray(1.0) - fresnel_view) * (1.0 - material.metallic);
+            let specular_weight = fresnel_view.max_component();
+            let total = specular_weight + diffuse_albedo.max_component();
+            let p_specular = if total > 0.0 { specular_weight / total } else { 1.0 };
+
+            if rng.f32() < p_specular {
+                if glossy {
+                    let alpha = brdf::alpha(material.roughness);
+                    let half = brdf::sample_microfacet(&normal, alpha, rng.f32(), rng.f32());
+                    let next = reflect(&direction, &half).normalize();
+                    let n_dot_l = normal.dot(&next);
+                    if n_dot_l <= 0.0 {
+                        break;
+                    }
+                    let v_dot_h = view.dot(&half).max(1e-4);
+                    let n_dot_h = normal.dot(&half).max(1e-4);
+                    // D se cancela con la pdf del microfacet muestreado
+                    let weight = brdf::fresnel_schlick(specular_color, v_dot_h)
+                        * (4.0 * brdf::smith_visibility(n_dot_v, n_dot_l, alpha) * n_dot_l * v_dot_h / n_dot_h);
+                    throughput *= weight / p_specular;
+                    direction = next;
+                    cone = cone.scatter(alpha);
+                    after_diffuse = true;
+                } else {
+                    throughput *= fresnel_view / p_specular;
+                    direction = reflect(&direction, &normal).normalize();
+                    after_diffuse = false;
+                }
+            } else {
+                // Con muestreo coseno, BRDF * coseno / pdf se reduce al albedo
+                throughput *= diffuse_albedo / (1.0 - p_specular);
+                direction = cosine_sample_hemisphere(&normal, rng);
+                cone = cone.scatter(DIFFUSE_SPREAD);
+                after_diffuse = true;
+            }
+        }
+        origin = offset_origin(&intersect.point, &intersect.normal, &direction);
+
+        if bounce >= ROULETTE_START {
+            let survival = throughput.max_component().min(0.95);
+            if rng.f32() >= survival {
+                break;
+            }
+            throughput = throughput / survival;
+        }
+    }
+
+    radiance
+}
+
+/// Which light transport algorithm `Renderer` runs for every pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// `cast_ray`: fast and deterministic, one sample per pixel is enough.
+    #[default]
+    Whitted,
+    /// `trace_path`: global illumination, noisy at low sample counts.
+    PathTracer,
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Integrator, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "whitted" => Ok(Integrator::Whitted),
+            "path" => Ok(Integrator::PathTracer),
+            _ => Err(format!("unknown integrator `{}` (expected whitted or path)", name)),
+        }
+    }
+}
+
+impl fmt::Display for Integrator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Integrator::Whitted => "whitted",
+            Integrator::PathTracer => "path",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Turns a camera and a scene into pixels. Holds the settings that are not part
+/// of the scene itself.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    pub integrator: Integrator,
+    /// Paths per camera ray for the path tracer. Whitted is deterministic and ignores it.
+    pub samples: u32,
+    /// Camera rays per pixel and how they are combined, for `render`.
+    pub antialiasing: AntiAliasing,
+    /// When set, `render` spends samples where the image is noisy instead of
+    /// using `antialiasing` and `samples` uniformly.
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Cap on the shadow rays per light and shading point. `render_progressive`
+    /// uses 1 when this is unset and lets the accumulated passes average them.
+    pub shadow_samples: Option<u32>,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            fov: PI / 3.0,
+            integrator: Integrator::Whitted,
+            samples: 16,
+            antialiasing: AntiAliasing::default(),
+            adaptive: None,
+            shadow_samples: None,
+        }
+    }
+}
+
+impl Renderer {
+    pub fn new(fov: f32, integrator: Integrator, samples: u32, antialiasing: AntiAliasing) -> Self {
+        Renderer { fov, integrator, samples, antialiasing, adaptive: None, shadow_samples: None }
+    }
+
+    /// One pixel's worth of radiance along the given camera ray.
+    pub fn radiance(&self, origin: &Vec3, direction: &Vec3, scene: &Scene, rng: &mut Rng, cone: RayCone) -> Spectrum {
+        match self.integrator {
+            Integrator::Whitted => cast_ray(origin, direction, scene, 0, cone, self.shadow_samples),
+            Integrator::PathTracer => {
+                let samples = self.samples.max(1);
+                let total: Spectrum = (0..samples).map(|_| self.sample(origin, direction, scene, rng, cone)).sum();
+                total / samples as f32
+            }
+        }
+    }
+
+    /// A single estimate from the integrator: one path, or one Whitted ray.
+    fn sample(&self, origin: &Vec3, direction: &Vec3, scene: &Scene, rng: &mut Rng, cone: RayCone) -> Spectrum {
+        match self.integrator {
+            Integrator::Whitted => cast_ray(origin, direction, scene, 0, cone, self.shadow_samples),
+            Integrator::PathTracer => trace_path(origin, direction, scene, rng, cone, self.shadow_samples),
+        }
+    }
+
+    /// Cone of a camera ray: one pixel wide when the image is `height` pixels tall.
+    pub fn pixel_cone(&self, height: usize) -> RayCone {
+        RayCone { width: 0.0, spread: 2.0 * (self.fov / 2.0).tan() / height.max(1) as f32 }
+    }
+
+    /// World-space direction of the ray through pixel (x, y).
+    pub fn primary_ray(&self, scene: &Scene, x: f32, y: f32, width: usize, height: usize) -> Vec3 {
+        let aspect_ratio = width as f32 / height as f32;
+        let perspective_scale = (self.fov / 2.0).tan();
+
+        let screen_x = (2.0 * x) / width as f32 - 1.0;
+        let screen_y = -(2.0 * y) / height as f32 + 1.0;
+        let screen_x = screen_x * aspect_ratio * perspective_scale;
+        let screen_y = screen_y * perspective_scale;
+
+        let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
+        scene.camera.basis_change(&ray_direction)
+    }
+
+    /// Renders every pixel in parallel with rayon.
+    pub fn render(&self, framebuffer: &mut FrameBuffer, scene: &Scene) {
+        if let Some(adaptive) = &self.adaptive {
+            self.render_adaptive(framebuffer, scene, adaptive);
+            return;
+        }
+        framebuffer.hdr = self.trace_pixels(framebuffer.width, framebuffer.height, scene, None);
+        // Con el path tracer cada rayo del pixel promedia `samples` caminos
+        let paths_per_ray = match self.integrator {
+            Integrator::Whitted => 1,
+            Integrator::PathTracer => self.samples.max(1),
+        };
+        let rays_per_pixel = self.antialiasing.grid.max(1).pow(2);
+        framebuffer.sample_counts = vec![rays_per_pixel * paths_per_ray; framebuffer.hdr.len()];
+        framebuffer.resolve();
+    }
+
+    /// Samples every pixel in rounds, dropping the ones whose estimate has
+    /// converged. Each sample is a single jittered ray inside the pixel; the
+    /// count each pixel ended up with is left in `framebuffer.sample_counts`.
+    fn render_adaptive(&self, framebuffer: &mut FrameBuffer, scene: &Scene, settings: &AdaptiveSampling) {
+        let start = Instant::now();
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let pixel_count = width * height;
+        let mut estimates = vec![PixelEstimate::default(); pixel_count];
+        let mut active: Vec<usize> = (0..pixel_count).collect();
+        let mut round = 0u64;
+        let cone = self.pixel_cone(height);
+
+        while !active.is_empty() {
+            let batch = if round == 0 { settings.min_samples.max(1) } else { settings.batch.max(1) };
+            let updated: Vec<PixelEstimate> = active
+                .par_iter()
+                .map(|&i| {
+                    let (x, y) = (i % width, i / width);
+                    let mut rng = Rng::with_seed(round * pixel_count as u64 + i as u64);
+                    let mut estimate = estimates[i];
+                    let remaining = settings.max_samples.max(1).saturating_sub(estimate.count);
+                    for _ in 0..batch.min(remaining) {
+                        let direction = self.primary_ray(scene, x as f32 + rng.f32(), y as f32 + rng.f32(), width, height);
+                        estimate.add(self.sample(&scene.camera.eye, &direction, scene, &mut rng, cone));
+                    }
+                    estimate
+                })
+                .collect();
+            for (&i, estimate) in active.iter().zip(updated) {
+                estimates[i] = estimate;
+            }
+
+            active.retain(|&i| !settings.is_converged(&estimates[i]));
+            round += 1;
+            if settings.time_budget.is_some_and(|budget| start.elapsed().as_secs_f32() >= budget) {
+                break;
+            }
+        }
+
+        framebuffer.hdr = estimates.iter().map(PixelEstimate::mean).collect();
+        framebuffer.sample_counts = estimates.iter().map(|estimate| estimate.count).collect();
+        framebuffer.resolve();
+    }
+
+    /// Adds one more pass, with a random offset inside every pixel, to the
+    /// framebuffer's accumulation buffer. Called once per frame while the view
+    /// doesn't change, the image converges to an antialiased, noise-free result.
+    /// Soft shadows converge the same way, from one shadow ray per light and pass.
+    pub fn render_progressive(&self, framebuffer: &mut FrameBuffer, scene: &Scene) {
+        let pass = framebuffer.sample_count;
+        let renderer = Renderer { shadow_samples: self.shadow_samples.or(Some(1)), ..self.clone() };
+        let samples = renderer.trace_pixels(framebuffer.width, framebuffer.height, scene, Some(pass));
+        framebuffer.accumulate(&samples);
+    }
+
+    /// Radiance for every pixel, in parallel with rayon. Without a `pass` every
+    /// pixel is supersampled as `antialiasing` says; with one it gets a single ray
+    /// at a random spot inside the pixel.
+    fn trace_pixels(&self, width: usize, height: usize, scene: &Scene, pass: Option<u32>) -> Vec<Spectrum> {
+        let pixel_count = width * height;
+        let cone = self.pixel_cone(height);
+        (0..pixel_count)
+            .into_par_iter()
+            .map(|i| {
+                let x = i % width;
+                let y = i / width;
+                // Semilla por pixel (y por pasada) para que el resultado no dependa del orden de rayon
+                let seed = pass.unwrap_or(0) as u64 * pixel_count as u64 + i as u64;
+                let mut rng = Rng::with_seed(seed);
+                if pass.is_some() {
+                    let direction = self.primary_ray(scene, x as f32 + rng.f32(), y as f32 + rng.f32(), width, height);
+                    return self.radiance(&scene.camera.eye, &direction, scene, &mut rng, cone);
+                }
+                self.filtered_pixel(x, y, width, height, scene, &mut rng)
+            })
+            .collect()
+    }
+
+    /// Weighted average of the pixel's samples, weights from the reconstruction filter.
+    fn filtered_pixel(&self, x: usize, y: usize, width: usize, height: usize, scene: &Scene, rng: &mut Rng) -> Spectrum {
+        let offsets = self.antialiasing.offsets(|| rng.f32());
+        let filter = self.antialiasing.filter;
+        let (center_x, center_y) = (x as f32 + 0.5, y as f32 + 0.5);
+
+        let mut total = Spectrum::black();
+        let mut total_weight = 0.0;
+        let mut mean = Spectrum::black();
+        for &(dx, dy) in &offsets {
+            let direction = self.primary_ray(scene, center_x + dx, center_y + dy, width, height);
+            // Cast the ray and get the color for the current pixel
+            let sample = self.radiance(&scene.camera.eye, &direction, scene, rng, self.pixel_cone(height));
+            let weight = filter.weight(dx, dy);
+            total += sample * weight;
+            total_weight += weight;
+            mean += sample;
+        }
+
+        // Con Mitchell los pesos negativos pueden casi anularse; ahi mejor el promedio simple
+        if total_weight.abs() > 1e-4 {
+            total / total_weight
+        } else {
+            mean / offsets.len() as f32
+        }
+    }
+}
+
+/// Renders with the default settings.
+pub fn render_parallel(framebuffer: &mut FrameBuffer, scene: &Scene) {
+    Renderer::default().render(framebuffer, scene);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::quad::Quad;
+    use crate::rayintersect::RayIntersect;
+    use crate::scene::Sky;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn fresnel_is_total_beyond_the_critical_angle() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        // Saliendo del vidrio casi rasante: mas alla del angulo critico de ~41.8 grados
+        let grazing = Vec3::new(1.0, 0.2, 0.0).normalize();
+        assert_eq!(fresnel(&grazing, &normal, 1.5), 1.0);
+        // De frente desde el aire: ((1 - 1.5) / (1 + 1.5))^2
+        let head_on = fresnel(&Vec3::new(0.0, -1.0, 0.0), &normal, 1.5);
+        assert!((head_on - 0.04).abs() < 1e-6);
+    }
+
+    fn scene_with(objects: Vec<Box<dyn RayIntersect + Sync>>) -> Scene {
+        let camera = Camera::new(Vec3::zeros(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), false);
+        let sky = Sky { day_color: Color::new(135, 206, 235), night_color: Color::new(0, 0, 0), day_ambient: 0.0, night_ambient: 0.0 };
+        Scene::new(objects, camera, Vec::new(), Vec::new(), sky, true)
+    }
+
+    fn mean_path_radiance(scene: &Scene, direction: Vec3, paths: u64) -> Spectrum {
+        let cone = RayCone { width: 0.0, spread: 0.0 };
+        let total: Spectrum = (0..paths)
+            .map(|seed| trace_path(&Vec3::zeros(), &direction, scene, &mut Rng::with_seed(seed), cone, None))
+            .sum();
+        total / paths as f32
+    }
+
+    #[test]
+    fn paths_that_miss_everything_see_the_sky() {
+        let scene = scene_with(Vec::new());
+        let radiance = mean_path_radiance(&scene, Vec3::new(0.3, 0.5, -1.0).normalize(), 4);
+        assert_eq!(radiance, scene.sky_color());
+    }
+
+    #[test]
+    fn roulette_keeps_the_glowing_furnace_unbiased() {
+        // Dentro de una esfera que emite 1 y refleja `albedo`, la radiancia es la serie 1 / (1 - albedo).
+        // Con un espejo sin perdidas y F0 casi nulo, Fresnel solo suma algo de reflejo en los rebotes rasantes
+        let base = Color::new(231, 231, 231);
+        let material = Material::pbr(base, 0.0, 0.0, 1.001, 0.0).with_emission(Color::new(255, 255, 255), 1.0);
+        let scene = scene_with(vec![Box::new(Sphere::new(Vec3::zeros(), 1.0, material))]);
+        let albedo = Spectrum::from_srgb(base).r;
+        let expected = 1.0 / (1.0 - albedo);
+        let radiance = mean_path_radiance(&scene, Vec3::new(0.0, 0.0, -1.0), 20_000);
+        // Cortar los caminos sin dividir por la probabilidad de sobrevivir daria alrededor de 3.2
+        assert!(radiance.r >= expected && radiance.r < 1.1 * expected, "{} vs {}", radiance.r, expected);
+    }
+
+    fn light_above(distance: f32) -> LightSample {
+        LightSample { direction: Vec3::new(0.0, 1.0, 0.0), distance, intensity: 1.0 }
+    }
+
+    fn glass(absorption: f32) -> Material {
+        let mut material = Material::pbr(Color::new(255, 188, 0), 0.0, 0.0, 1.5, 1.0);
+        material.absorption = absorption;
+        material
+    }
+
+    #[test]
+    fn shadows_through_glass_follow_beer_lambert() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let through = |thickness: f32| {
+            let block = Cube::new(Vec3::new(-1.0, 1.0, -1.0), Vec3::new(1.0, 1.0 + thickness, 1.0), glass(0.8));
+            let scene = scene_with(vec![Box::new(block)]);
+            shadow_transmittance(&Vec3::zeros(), &up, &light_above(10.0), &scene)
+        };
+        let (thin, thick) = (through(1.0), through(3.0));
+        // El rojo del vidrio pasa entero; el verde (~0.5 lineal) se absorbe con exp(-a (1 - c) d) y el azul del todo
+        assert!((thin.r - 1.0).abs() < 1e-4 && (thick.r - 1.0).abs() < 1e-4);
+        let green = Spectrum::from_srgb(Color::new(0, 188, 0)).g;
+        let expected = (-0.8 * (1.0 - green) * 2.0).exp();
+        assert!((thick.g / thin.g - expected).abs() < 1e-3, "{} vs {}", thick.g / thin.g, expected);
+        assert_eq!((thin.b, thick.b), (0.0, 0.0));
+    }
+
+    #[test]
+    fn opaque_blockers_and_thin_panes_in_the_way() {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let stone = Cube::new(Vec3::new(-1.0, 1.0, -1.0), Vec3::new(1.0, 2.0, 1.0), Material::black());
+        let scene = scene_with(vec![Box::new(stone)]);
+        assert_eq!(shadow_transmittance(&Vec3::zeros(), &up, &light_above(10.0), &scene), Spectrum::black());
+        // Si la luz esta antes del bloque no hay sombra
+        assert_eq!(shadow_transmittance(&Vec3::zeros(), &up, &light_above(0.5), &scene), Spectrum::gray(1.0));
+
+        // Una lamina tiñe pero no absorbe, por mas absorcion que tenga
+        let pane = Quad::new(Vec3::new(-1.0, 1.0, -1.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0), glass(5.0));
+        let scene = scene_with(vec![Box::new(pane)]);
+        let tinted = shadow_transmittance(&Vec3::zeros(), &up, &light_above(10.0), &scene);
+        assert!((tinted.r - 1.0).abs() < 1e-4 && tinted.g > 0.4 && tinted.b == 0.0, "{:?}", tinted);
+    }
+}
@@ -0,0 +1,64 @@
+use serde::Deserialize;
+
+/// How texels are combined. All three read from the mip level that matches
+/// the footprint of the ray, so distant surfaces don't shimmer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum TextureFilter {
+    /// The closest texel: blocky up close, like the textures were drawn.
+    #[default]
+    Nearest,
+    /// Weighted average of the four closest texels.
+    Bilinear,
+    /// Bilinear in the two closest mip levels, blended between them.
+    Trilinear,
+}
+
+/// What happens to texture coordinates outside [0, 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum WrapMode {
+    /// Tiles the texture. Atlas tiles clamp instead, see `Texture::sample_region`.
+    #[default]
+    Repeat,
+    /// Sticks to the edge texels.
+    Clamp,
+    /// Repeats, flipping every other copy.
+    Mirror,
+}
+
+impl WrapMode {
+    /// Texel index along an axis `size` texels long, for any integer index.
+    pub fn apply(self, index: i64, size: u32) -> u32 {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Clamp => index.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let period = index.rem_euclid(2 * size);
+                if period < size { period } else { 2 * size - 1 - period }
+            }
+        };
+        wrapped as u32
+    }
+}
+
+/// Filtering and wrapping settings for a texture lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sampler {
+    pub filter: TextureFilter,
+    pub wrap: WrapMode,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_modes_map_indices_into_range() {
+        let indices = [-5, -1, 0, 3, 4, 7, 9];
+        let apply = |mode: WrapMode| indices.map(|index| mode.apply(index, 4));
+        assert_eq!(apply(WrapMode::Repeat), [3, 3, 0, 3, 0, 3, 1]);
+        assert_eq!(apply(WrapMode::Clamp), [0, 0, 0, 3, 3, 3, 3]);
+        assert_eq!(apply(WrapMode::Mirror), [3, 0, 0, 3, 3, 0, 1]);
+    }
+}
@@ -0,0 +1,1079 @@
+use image::ImageResult;
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::adaptive::AdaptiveSampling;
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::{Cube, Face};
+use crate::daylight::DayCycle;
+use crate::disk::Disk;
+use crate::display::DisplayTransform;
+use crate::filter::AntiAliasing;
+use crate::grid::{create_empty_grid, create_grid};
+use crate::light::{AreaLight, AreaShape, DirectionalLight, EmitterLight, Light, LightSource, SpotLight};
+use crate::material::{from_phong, AlphaMode, Material, TextureSlot};
+use crate::obj::{load_obj, ObjError};
+use crate::plane::Plane;
+use crate::quad::Quad;
+use crate::rayintersect::{RayIntersect, Intersect};
+use crate::sampler::Sampler;
+use crate::spectrum::Spectrum;
+use crate::sphere::Sphere;
+use crate::texture::{Texture, TextureRegion};
+use crate::triangle::Triangle;
+use crate::voxel::{PaletteFull, VoxelWorld};
+
+type Vec3Description = (f32, f32, f32);
+type ColorDescription = (u8, u8, u8);
+
+fn vec3(v: Vec3Description) -> Vec3 {
+    Vec3::new(v.0, v.1, v.2)
+}
+
+fn color(c: ColorDescription) -> Color {
+    Color::new(c.0, c.1, c.2)
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, io::Error),
+    Parse { path: PathBuf, line: usize, col: usize, field: String, message: String },
+    Texture(String, PathBuf, image::ImageError),
+    UnknownTexture { material: String, texture: String },
+    UnknownAtlas { material: String, texture: String },
+    TileOutOfRange { material: String, texture: String, tile: (u32, u32) },
+    UnknownMaterial { object: usize, material: String },
+    PaletteFull { object: usize },
+    /// A light sets two fields that can't be used together.
+    LightConflict { light: usize, first: &'static str, second: &'static str },
+    Mesh(PathBuf, ObjError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            SceneError::Parse { path, line, col, field, message } => {
+                if field.is_empty() || field == "." {
+                    write!(f, "{}:{}:{}: {}", path.display(), line, col, message)
+                } else {
+                    write!(f, "{}:{}:{}: in `{}`: {}", path.display(), line, col, field, message)
+                }
+            }
+            SceneError::Texture(name, path, error) => {
+                write!(f, "texture `{}` ({}): {}", name, path.display(), error)
+            }
+            SceneError::UnknownTexture { material, texture } => {
+                write!(f, "material `{}` uses unknown texture `{}`", material, texture)
+            }
+            SceneError::UnknownAtlas { material, texture } => {
+                write!(f, "material `{}` uses a tile of `{}`, which is not in `atlases`", material, texture)
+            }
+            SceneError::TileOutOfRange { material, texture, tile } => {
+                write!(f, "material `{}` uses tile {:?}, outside atlas `{}`", material, tile, texture)
+            }
+            SceneError::UnknownMaterial { object, material } => {
+                write!(f, "objects[{}] uses unknown material `{}`", object, material)
+            }
+            SceneError::PaletteFull { object } => write!(f, "objects[{}]: {}", object, PaletteFull),
+            SceneError::LightConflict { light, first, second } => {
+                write!(f, "lights[{}] sets both `{}` and `{}`", light, first, second)
+            }
+            SceneError::Mesh(path, error) => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraDescription {
+    pub eye: Vec3Description,
+    pub center: Vec3Description,
+    #[serde(default = "default_up")]
+    pub up: Vec3Description,
+}
+
+fn default_up() -> Vec3Description {
+    (0.0, 1.0, 0.0)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SkyDescription {
+    pub day_color: ColorDescription,
+    pub night_color: ColorDescription,
+    /// Fraction of the sky color that reaches every surface as ambient light.
+    pub day_ambient: f32,
+    pub night_ambient: f32,
+    pub is_day: bool,
+}
+
+impl Default for SkyDescription {
+    fn default() -> Self {
+        SkyDescription {
+            day_color: (135, 206, 235),
+            night_color: (4, 12, 36),
+            day_ambient: 0.3,
+            night_ambient: 0.5,
+            is_day: true,
+        }
+    }
+}
+
+/// Sun and moon that follow the time of day. See `DayCycle`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SunDescription {
+    /// Hours since midnight.
+    pub time: f32,
+    /// Direction the sun rises from.
+    pub east: Vec3Description,
+    /// How far from the zenith the noon sun is, in degrees.
+    pub tilt: f32,
+    pub sun_intensity: f32,
+    pub moon_intensity: f32,
+    pub moon_color: ColorDescription,
+    pub dusk_color: ColorDescription,
+    /// Apparent radius of the sun and moon, in degrees. 0 gives hard shadows.
+    pub angular_radius: f32,
+    pub shadow_samples: u32,
+}
+
+impl Default for SunDescription {
+    fn default() -> Self {
+        let cycle = DayCycle::default();
+        SunDescription {
+            time: cycle.time,
+            east: (cycle.east.x, cycle.east.y, cycle.east.z),
+            tilt: cycle.tilt.to_degrees(),
+            sun_intensity: cycle.sun_intensity,
+            moon_intensity: cycle.moon_intensity,
+            moon_color: (cycle.moon_color.r, cycle.moon_color.g, cycle.moon_color.b),
+            dusk_color: (cycle.dusk_color.r, cycle.dusk_color.g, cycle.dusk_color.b),
+            angular_radius: cycle.angular_radius.to_degrees(),
+            shadow_samples: 16,
+        }
+    }
+}
+
+impl SunDescription {
+    fn build(&self) -> DayCycle {
+        DayCycle {
+            time: self.time,
+            east: vec3(self.east),
+            tilt: self.tilt.to_radians(),
+            sun_intensity: self.sun_intensity,
+            moon_intensity: self.moon_intensity,
+            angular_radius: self.angular_radius.to_radians(),
+            shadow_samples: self.shadow_samples,
+            moon_color: color(self.moon_color),
+            dusk_color: color(self.dusk_color),
+        }
+    }
+}
+
+/// A material, written either with the metallic-roughness parameters or with
+/// the older Phong ones (`specular` exponent and `albedo` weights), which get
+/// converted. Any of `metallic`, `roughness` and `transmission` that is given
+/// replaces the converted value; with `metallic` given, the diffuse weight in
+/// `albedo` no longer darkens the base color.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaterialDescription {
+    /// Base color.
+    pub diffuse: ColorDescription,
+    pub specular: f32,
+    pub albedo: [f32; 4],
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+    pub transmission: Option<f32>,
+    pub texture: Option<String>,
+    pub sampler: Sampler,
+    pub refractive_index: f32,
+    pub emission: ColorDescription,
+    pub emission_strength: f32,
+    /// Beer-Lambert absorption inside transparent materials; 0 only tints
+    /// shadows at the surface.
+    pub absorption: f32,
+    /// Texture with tangent-space normals, such as a resource pack's `_n.png`.
+    pub normal_map: Option<String>,
+    /// LabPBR specular texture (`_s.png`): smoothness in red, metal in green.
+    pub specular_map: Option<String>,
+    /// Texture with the emitted color, scaled by `emission_strength`.
+    pub emission_map: Option<String>,
+    /// (column, row) of `texture` to use, when it is one of the `atlases`.
+    pub tile: Option<(u32, u32)>,
+    /// Textures for single block faces; the rest use `texture`.
+    pub faces: Option<FacesDescription>,
+    /// Cutouts (`Mask(cutoff: 0.5)`) or see-through blending from the texture alpha.
+    pub alpha_mode: AlphaMode,
+}
+
+/// Per-face textures of a block. The named sides win over `side`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FacesDescription {
+    pub top: Option<FaceTextureDescription>,
+    pub bottom: Option<FaceTextureDescription>,
+    pub side: Option<FaceTextureDescription>,
+    pub north: Option<FaceTextureDescription>,
+    pub south: Option<FaceTextureDescription>,
+    pub east: Option<FaceTextureDescription>,
+    pub west: Option<FaceTextureDescription>,
+}
+
+impl FacesDescription {
+    fn get(&self, face: Face) -> Option<&FaceTextureDescription> {
+        let named = match face {
+            Face::Top => return self.top.as_ref(),
+            Face::Bottom => return self.bottom.as_ref(),
+            Face::North => &self.north,
+            Face::South => &self.south,
+            Face::East => &self.east,
+            Face::West => &self.west,
+        };
+        named.as_ref().or(self.side.as_ref())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FaceTextureDescription {
+    pub texture: String,
+    #[serde(default)]
+    pub tile: Option<(u32, u32)>,
+}
+
+/// A texture cut into a grid of equal tiles. Minecraft's terrain.png is 16 x 16.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AtlasDescription {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Default for AtlasDescription {
+    fn default() -> Self {
+        AtlasDescription { columns: 16, rows: 16 }
+    }
+}
+
+impl Default for MaterialDescription {
+    fn default() -> Self {
+        MaterialDescription {
+            diffuse: (128, 128, 128),
+            specular: 2.0,
+            albedo: [0.9, 0.1, 0.0, 0.0],
+            metallic: None,
+            roughness: None,
+            transmission: None,
+            texture: None,
+            sampler: Sampler::default(),
+            refractive_index: 1.0,
+            emission: (0, 0, 0),
+            emission_strength: 0.0,
+            absorption: 0.0,
+            normal_map: None,
+            specular_map: None,
+            emission_map: None,
+            tile: None,
+            faces: None,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LightDescription {
+    /// Where the light is; the center of `shape` for area lights.
+    pub position: Vec3Description,
+    /// White when neither this nor `kelvin` is set; with `kelvin`, a gel
+    /// that filters the blackbody color.
+    #[serde(default)]
+    pub color: Option<ColorDescription>,
+    /// Color temperature in Kelvin.
+    #[serde(default)]
+    pub kelvin: Option<f32>,
+    pub intensity: f32,
+    /// Intensity when it is night. Defaults to the day intensity.
+    #[serde(default)]
+    pub night_intensity: Option<f32>,
+    /// Turns the point light into an area light with soft shadows.
+    #[serde(default)]
+    pub shape: Option<LightShapeDescription>,
+    /// Shadow rays per shading point for area lights.
+    #[serde(default = "default_shadow_samples")]
+    pub shadow_samples: u32,
+    /// Turns the point light into a spot light. Not allowed with `shape`.
+    #[serde(default)]
+    pub spot: Option<SpotDescription>,
+    /// Distance at which a point or spot light has faded out completely. Not
+    /// allowed with `shape`.
+    #[serde(default)]
+    pub range: Option<f32>,
+}
+
+/// Cone of a spot light; the angles are half-angles in degrees.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpotDescription {
+    pub direction: Vec3Description,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+}
+
+fn default_shadow_samples() -> u32 {
+    16
+}
+
+fn default_emitter_samples() -> u32 {
+    4
+}
+
+/// One `EmitterLight` per emission color, so the light color stays exact while
+/// the number of lights doesn't grow with the number of glowing blocks.
+fn group_emitters(cubes: Vec<Cube>, samples: u32) -> Vec<EmitterLight> {
+    let mut groups: Vec<((u8, u8, u8), Vec<Cube>)> = Vec::new();
+    for cube in cubes {
+        let color = cube.material.emission_color();
+        let key = (color.r, color.g, color.b);
+        match groups.iter_mut().find(|(group, _)| *group == key) {
+            Some((_, group)) => group.push(cube),
+            None => groups.push((key, vec![cube])),
+        }
+    }
+    groups.into_iter().map(|(_, cubes)| EmitterLight::new(cubes, samples)).collect()
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum LightShapeDescription {
+    Rect { edge_u: Vec3Description, edge_v: Vec3Description },
+    Disk { normal: Vec3Description, radius: f32 },
+    Sphere { radius: f32 },
+}
+
+impl LightDescription {
+    fn color(&self) -> Color {
+        match (self.kelvin, self.color) {
+            (Some(kelvin), Some(gel)) => Color::from_kelvin(kelvin) * color(gel),
+            (Some(kelvin), None) => Color::from_kelvin(kelvin),
+            (None, Some(rgb)) => color(rgb),
+            (None, None) => Color::new(255, 255, 255),
+        }
+    }
+
+    /// Rejects fields that would otherwise be silently dropped.
+    fn check(&self, index: usize) -> Result<(), SceneError> {
+        let conflict = |first, second| SceneError::LightConflict { light: index, first, second };
+        if self.shape.is_some() {
+            if self.spot.is_some() {
+                return Err(conflict("shape", "spot"));
+            }
+            if self.range.is_some() {
+                return Err(conflict("shape", "range"));
+            }
+        }
+        Ok(())
+    }
+
+    fn build(&self, intensity: f32) -> Box<dyn LightSource> {
+        let position = vec3(self.position);
+        let shape = match self.shape {
+            None => return self.build_point(position, intensity),
+            Some(LightShapeDescription::Rect { edge_u, edge_v }) => {
+                AreaShape::Rect { center: position, edge_u: vec3(edge_u), edge_v: vec3(edge_v) }
+            }
+            Some(LightShapeDescription::Disk { normal, radius }) => {
+                AreaShape::Disk { center: position, normal: vec3(normal), radius }
+            }
+            Some(LightShapeDescription::Sphere { radius }) => AreaShape::Sphere { center: position, radius },
+        };
+        Box::new(AreaLight::new(shape, self.color(), intensity, self.shadow_samples))
+    }
+
+    fn build_point(&self, position: Vec3, intensity: f32) -> Box<dyn LightSource> {
+        match self.spot {
+            Some(spot) => {
+                let mut light = SpotLight::new(
+                    position,
+                    vec3(spot.direction),
+                    self.color(),
+                    intensity,
+                    spot.inner_angle.to_radians(),
+                    spot.outer_angle.to_radians(),
+                );
+                light.range = self.range;
+                Box::new(light)
+            }
+            None => {
+                let mut light = Light::new(position, self.color(), intensity);
+                light.range = self.range;
+                Box::new(light)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum GridPlane {
+    Xy,
+    Xz,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VoxelFillDescription {
+    pub min: (i32, i32, i32),
+    pub max: (i32, i32, i32),
+    pub material: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ObjectDescription {
+    Cube {
+        min: Vec3Description,
+        max: Vec3Description,
+        material: String,
+    },
+    /// Same as `create_grid`, or `create_empty_grid` when `hollow` is set.
+    Grid {
+        min: Vec3Description,
+        max: Vec3Description,
+        #[serde(default = "default_cube_length")]
+        cube_length: f32,
+        plane: GridPlane,
+        size: usize,
+        material: String,
+        #[serde(default)]
+        hollow: bool,
+    },
+    Sphere {
+        center: Vec3Description,
+        radius: f32,
+        material: String,
+    },
+    Plane {
+        point: Vec3Description,
+        normal: Vec3Description,
+        material: String,
+    },
+    Quad {
+        corner: Vec3Description,
+        edge_u: Vec3Description,
+        edge_v: Vec3Description,
+        material: String,
+    },
+    Disk {
+        center: Vec3Description,
+        normal: Vec3Description,
+        radius: f32,
+        material: String,
+    },
+    Triangle {
+        a: Vec3Description,
+        b: Vec3Description,
+        c: Vec3Description,
+        material: String,
+    },
+    /// OBJ file; materials come from its MTL library.
+    Mesh {
+        path: String,
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default)]
+        translation: Vec3Description,
+    },
+    Voxels {
+        #[serde(default = "default_voxel_origin")]
+        origin: Vec3Description,
+        blocks: Vec<VoxelFillDescription>,
+    },
+}
+
+fn default_cube_length() -> f32 {
+    1.0
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+fn default_voxel_origin() -> Vec3Description {
+    (-0.5, -0.5, -0.5)
+}
+
+/// Everything a scene file can hold. Paths inside it are relative to the file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    #[serde(default)]
+    pub sky: SkyDescription,
+    /// Replaces `sky.is_day` with a continuous day cycle.
+    #[serde(default)]
+    pub sun: Option<SunDescription>,
+    #[serde(default)]
+    pub display: DisplayTransform,
+    #[serde(default)]
+    pub antialiasing: AntiAliasing,
+    #[serde(default)]
+    pub adaptive: Option<AdaptiveSampling>,
+    #[serde(default)]
+    pub textures: HashMap<String, String>,
+    /// Textures that are tile grids, by texture name.
+    #[serde(default)]
+    pub atlases: HashMap<String, AtlasDescription>,
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialDescription>,
+    #[serde(default)]
+    pub lights: Vec<LightDescription>,
+    /// Shadow rays per shading point toward each group of emissive cubes.
+    #[serde(default = "default_emitter_samples")]
+    pub emitter_samples: u32,
+    #[serde(default)]
+    pub objects: Vec<ObjectDescription>,
+    #[serde(skip)]
+    base_dir: PathBuf,
+}
+
+impl SceneDescription {
+    pub fn load(path: &str) -> Result<SceneDescription, SceneError> {
+        let path = PathBuf::from(path);
+        let text = fs::read_to_string(&path).map_err(|error| SceneError::Io(path.clone(), error))?;
+
+        let parse_error = |field: String, error: ron::error::SpannedError| SceneError::Parse {
+            path: path.clone(),
+            line: error.position.line,
+            col: error.position.col,
+            field,
+            message: error.code.to_string(),
+        };
+
+        let mut deserializer = ron::Deserializer::from_str(&text).map_err(|error| parse_error(String::new(), error))?;
+        let mut description: SceneDescription = match serde_path_to_error::deserialize(&mut deserializer) {
+            Ok(description) => description,
+            Err(error) => {
+                let field = error.path().to_string();
+                let spanned = deserializer.span_error(error.into_inner());
+                return Err(parse_error(field, spanned));
+            }
+        };
+        deserializer
+            .end()
+            .map_err(|error| parse_error(String::new(), deserializer.span_error(error)))?;
+
+        description.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Ok(description)
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.base_dir.join(path)
+    }
+
+    /// Loads the textures the materials use and builds every named material.
+    pub fn materials(&self) -> Result<HashMap<String, Material>, SceneError> {
+        // Cada textura se lee cuando un material la usa: con sRGB si son colores
+        // y sin el si son mapas de normales o especulares, que guardan datos
+        let mut color_textures = HashMap::new();
+        let mut data_textures = HashMap::new();
+        let mut materials = HashMap::new();
+        for (name, m) in &self.materials {
+            let mut lookup = |texture: &String| self.texture(&mut color_textures, name, texture, Texture::load);
+            let texture = m.texture.as_ref().map(&mut lookup).transpose()?;
+            let emission_map = match &m.emission_map {
+                Some(map) => Some(self.map_slot(name, map, lookup(map)?, m.tile)?),
+                None => None,
+            };
+            let normal_map = match &m.normal_map {
+                Some(map) => Some(self.map_slot(name, map, self.texture(&mut data_textures, name, map, Texture::load_linear)?, m.tile)?),
+                None => None,
+            };
+            let specular_map = match &m.specular_map {
+                Some(map) => Some(self.map_slot(name, map, self.texture(&mut data_textures, name, map, Texture::load_linear)?, m.tile)?),
+                None => None,
+            };
+            let region = match &m.texture {
+                Some(texture) => self.region(name, texture, m.tile)?,
+                None => TextureRegion::FULL,
+            };
+            let faces = match &m.faces {
+                Some(faces) => {
+                    let mut resolved: [Option<TextureSlot>; 6] = Default::default();
+                    for face in Face::ALL {
+                        if let Some(description) = faces.get(face) {
+                            let texture = lookup(&description.texture)?;
+                            let region = self.region(name, &description.texture, description.tile)?;
+                            resolved[face as usize] = Some(TextureSlot { texture, region, shares_layout: false });
+                        }
+                    }
+                    Some(resolved)
+                }
+                None => None,
+            };
+            let phong = from_phong(m.specular, m.albedo, m.refractive_index);
+            materials.insert(
+                name.clone(),
+                Material::pbr(
+                    color(m.diffuse),
+                    m.metallic.unwrap_or(0.0),
+                    m.roughness.unwrap_or(phong.roughness),
+                    phong.refractive_index,
+                    m.transmission.unwrap_or(phong.transmission),
+                )
+                .with_base_weight(if m.metallic.is_some() { 1.0 } else { phong.base_weight })
+                .with_texture(texture)
+                .with_region(region)
+                .with_faces(faces)
+                .with_alpha_mode(m.alpha_mode)
+                .with_sampler(m.sampler)
+                .with_emission(color(m.emission), m.emission_strength)
+                .with_emission_map(emission_map)
+                .with_absorption(m.absorption)
+                .with_normal_map(normal_map)
+                .with_specular_map(specular_map),
+            );
+        }
+        Ok(materials)
+    }
+
+    /// The part of `texture` a material uses: the whole of it, or `tile` of an atlas.
+    fn region(&self, material: &str, texture: &str, tile: Option<(u32, u32)>) -> Result<TextureRegion, SceneError> {
+        let Some((column, row)) = tile else {
+            return Ok(TextureRegion::FULL);
+        };
+        let atlas = self.atlases.get(texture).ok_or_else(|| SceneError::UnknownAtlas {
+            material: material.to_string(),
+            texture: texture.to_string(),
+        })?;
+        if column >= atlas.columns || row >= atlas.rows {
+            return Err(SceneError::TileOutOfRange {
+                material: material.to_string(),
+                texture: texture.to_string(),
+                tile: (column, row),
+            });
+        }
+        Ok(TextureRegion::tile(column, row, atlas.columns, atlas.rows))
+    }
+
+    /// Where a normal, specular or emission map is read. A map listed in
+    /// `atlases` is laid out like the material's texture and uses the same
+    /// tile; any other map is used whole, like a resource pack's `_n.png`.
+    fn map_slot(&self, material: &str, name: &str, texture: Arc<Texture>, tile: Option<(u32, u32)>) -> Result<TextureSlot, SceneError> {
+        if !self.atlases.contains_key(name) {
+            return Ok(TextureSlot::whole(texture));
+        }
+        let region = self.region(material, name, tile)?;
+        Ok(TextureSlot { texture, region, shares_layout: true })
+    }
+
+    /// Loads the texture called `texture` with `load`, once per name and cache.
+    fn texture(
+        &self,
+        cache: &mut HashMap<String, Arc<Texture>>,
+        material: &str,
+        texture: &String,
+        load: fn(&str) -> ImageResult<Texture>,
+    ) -> Result<Arc<Texture>, SceneError> {
+        if let Some(loaded) = cache.get(texture) {
+            return Ok(loaded.clone());
+        }
+        let path = self.textures.get(texture).ok_or_else(|| SceneError::UnknownTexture {
+            material: material.to_string(),
+            texture: texture.clone(),
+        })?;
+        let path = self.resolve(path);
+        let loaded = load(&path.to_string_lossy()).map_err(|error| SceneError::Texture(texture.clone(), path.clone(), error))?;
+        let loaded = Arc::new(loaded);
+        cache.insert(texture.clone(), loaded.clone());
+        Ok(loaded)
+    }
+
+    /// Every `Cube` and `Grid` in the scene, expanded into unit cubes made of
+    /// `materials`, as returned by `materials`.
+    pub fn cubes(&self, materials: &HashMap<String, Material>) -> Result<Vec<Cube>, SceneError> {
+        let mut cubes = Vec::new();
+        for (index, object) in self.objects.iter().enumerate() {
+            cubes.extend(expand_cubes(index, object, materials)?);
+        }
+        Ok(cubes)
+    }
+
+    pub fn build(&self) -> Result<Scene, SceneError> {
+        self.build_with(&self.materials()?)
+    }
+
+    /// Like `build`, reusing `materials` so their textures aren't loaded again.
+    pub fn build_with(&self, materials: &HashMap<String, Material>) -> Result<Scene, SceneError> {
+        let mut objects: Vec<Box<dyn RayIntersect + Sync>> = Vec::new();
+        let mut emissive = Vec::new();
+
+        for (index, object) in self.objects.iter().enumerate() {
+            let material = |name: &String| {
+                materials.get(name).cloned().ok_or_else(|| SceneError::UnknownMaterial {
+                    object: index,
+                    material: name.clone(),
+                })
+            };
+            let object: Box<dyn RayIntersect + Sync> = match object {
+                ObjectDescription::Cube { .. } | ObjectDescription::Grid { .. } => {
+                    let cubes = expand_cubes(index, object, materials)?;
+                    emissive.extend(cubes.iter().filter(|cube| cube.material.emission_strength > 0.0).cloned());
+                    objects.extend(cubes.into_iter().map(|cube| Box::new(cube) as Box<dyn RayIntersect + Sync>));
+                    continue;
+                }
+                ObjectDescription::Sphere { center, radius, material: name } => {
+                    Box::new(Sphere::new(vec3(*center), *radius, material(name)?))
+                }
+                ObjectDescription::Plane { point, normal, material: name } => {
+                    Box::new(Plane::new(vec3(*point), vec3(*normal), material(name)?))
+                }
+                ObjectDescription::Quad { corner, edge_u, edge_v, material: name } => {
+                    Box::new(Quad::new(vec3(*corner), vec3(*edge_u), vec3(*edge_v), material(name)?))
+                }
+                ObjectDescription::Disk { center, normal, radius, material: name } => {
+                    Box::new(Disk::new(vec3(*center), vec3(*normal), *radius, material(name)?))
+                }
+                ObjectDescription::Triangle { a, b, c, material: name } => {
+                    Box::new(Triangle::flat(vec3(*a), vec3(*b), vec3(*c), Arc::new(material(name)?)))
+                }
+                ObjectDescription::Mesh { path, scale, translation } => {
+                    let path = self.resolve(path);
+                    let mut mesh = load_obj(&path.to_string_lossy()).map_err(|error| SceneError::Mesh(path.clone(), error))?;
+                    mesh.transform(*scale, vec3(*translation));
+                    Box::new(mesh)
+                }
+                ObjectDescription::Voxels { origin, blocks } => {
+                    let mut world = VoxelWorld::new(vec3(*origin));
+                    let mut ids = HashMap::new();
+                    for fill in blocks {
+                        let id = match ids.get(&fill.material) {
+                            Some(&id) => id,
+                            None => {
+                                let id = world
+                                    .add_block_type(material(&fill.material)?)
+                                    .map_err(|_| SceneError::PaletteFull { object: index })?;
+                                ids.insert(fill.material.clone(), id);
+                                id
+                            }
+                        };
+                        world.fill([fill.min.0, fill.min.1, fill.min.2], [fill.max.0, fill.max.1, fill.max.2], id);
+                    }
+                    emissive.extend(world.emissive_cubes());
+                    Box::new(world)
+                }
+            };
+            objects.push(object);
+        }
+
+        for (index, light) in self.lights.iter().enumerate() {
+            light.check(index)?;
+        }
+        let day_lights = self.lights.iter().map(|l| l.build(l.intensity)).collect();
+        let night_lights = self
+            .lights
+            .iter()
+            .map(|l| l.build(l.night_intensity.unwrap_or(l.intensity)))
+            .collect();
+
+        let camera = Camera::new(vec3(self.camera.eye), vec3(self.camera.center), vec3(self.camera.up), false);
+        let sky = Sky {
+            day_color: color(self.sky.day_color),
+            night_color: color(self.sky.night_color),
+            day_ambient: self.sky.day_ambient,
+            night_ambient: self.sky.night_ambient,
+        };
+
+        let mut scene = Scene::new(objects, camera, day_lights, night_lights, sky, self.sky.is_day);
+        scene.display = self.display;
+        scene.antialiasing = self.antialiasing;
+        scene.adaptive = self.adaptive;
+        scene.emitters = group_emitters(emissive, self.emitter_samples);
+        scene.update_ambient_tint();
+        if let Some(sun) = &self.sun {
+            scene.set_day_cycle(sun.build());
+        }
+        Ok(scene)
+    }
+}
+
+/// The unit cubes a `Cube` or `Grid` stands for; none for any other object.
+fn expand_cubes(
+    index: usize,
+    object: &ObjectDescription,
+    materials: &HashMap<String, Material>,
+) -> Result<Vec<Cube>, SceneError> {
+    let material = |name: &String| {
+        materials.get(name).cloned().ok_or_else(|| SceneError::UnknownMaterial {
+            object: index,
+            material: name.clone(),
+        })
+    };
+
+    let cubes = match object {
+        ObjectDescription::Cube { min, max, material: name } => {
+            vec![Cube::new(vec3(*min), vec3(*max), material(name)?)]
+        }
+        ObjectDescription::Grid { min, max, cube_length, plane, size, material: name, hollow } => {
+            let mode = match plane {
+                GridPlane::Xy => 1,
+                GridPlane::Xz => 2,
+            };
+            let generator = if *hollow { create_empty_grid } else { create_grid };
+            generator(&mut vec3(*min), &mut vec3(*max), *cube_length, mode, *size, material(name)?)
+        }
+        ObjectDescription::Sphere { .. }
+        | ObjectDescription::Plane { .. }
+        | ObjectDescription::Quad { .. }
+        | ObjectDescription::Disk { .. }
+        | ObjectDescription::Triangle { .. }
+        | ObjectDescription::Mesh { .. }
+        | ObjectDescription::Voxels { .. } => Vec::new(),
+    };
+    Ok(cubes)
+}
+
+#[derive(Debug, Clone)]
+pub struct Sky {
+    pub day_color: Color,
+    pub night_color: Color,
+    pub day_ambient: f32,
+    pub night_ambient: f32,
+}
+
+impl Sky {
+    pub fn color(&self, is_day: bool) -> Color {
+        if is_day { self.day_color } else { self.night_color }
+    }
+
+    pub fn ambient(&self, is_day: bool) -> f32 {
+        if is_day { self.day_ambient } else { self.night_ambient }
+    }
+}
+
+pub struct Scene {
+    pub objects: Vec<Box<dyn RayIntersect + Sync>>,
+    pub bvh: Bvh,
+    pub camera: Camera,
+    pub day_lights: Vec<Box<dyn LightSource>>,
+    pub night_lights: Vec<Box<dyn LightSource>>,
+    pub sky: Sky,
+    pub is_day: bool,
+    /// How the scene wants to be shown; copied into the `FrameBuffer` before rendering.
+    pub display: DisplayTransform,
+    /// Supersampling the scene asks for in final renders.
+    pub antialiasing: AntiAliasing,
+    /// Adaptive sampling for final renders, if the scene wants it.
+    pub adaptive: Option<AdaptiveSampling>,
+    /// Emissive cubes, also present in `objects`, as lights. They are on day and night.
+    pub emitters: Vec<EmitterLight>,
+    /// Sun and moon driven by the time of day. Without it, `is_day` alone picks
+    /// the sky color and which of the light lists is on; with it, the lists are
+    /// blended by `daylight`.
+    pub day_cycle: Option<DayCycle>,
+    /// The sun and moon for the current time of day, kept in sync by `set_time_of_day`.
+    pub sky_lights: Vec<DirectionalLight>,
+    /// Color of the lights that are on, kept by `update_ambient_tint`.
+    pub ambient_tint: Spectrum,
+}
+
+impl Scene {
+    pub fn new(
+        objects: Vec<Box<dyn RayIntersect + Sync>>,
+        camera: Camera,
+        day_lights: Vec<Box<dyn LightSource>>,
+        night_lights: Vec<Box<dyn LightSource>>,
+        sky: Sky,
+        is_day: bool,
+    ) -> Self {
+        let bvh = Bvh::new(&objects);
+        let mut scene = Scene {
+            objects,
+            bvh,
+            camera,
+            day_lights,
+            night_lights,
+            sky,
+            is_day,
+            display: DisplayTransform::default(),
+            antialiasing: AntiAliasing::default(),
+            adaptive: None,
+            emitters: Vec::new(),
+            day_cycle: None,
+            sky_lights: Vec::new(),
+            ambient_tint: Spectrum::gray(1.0),
+        };
+        scene.update_ambient_tint();
+        scene
+    }
+
+    /// Drives the scene with a day cycle, starting at the cycle's own time.
+    pub fn set_day_cycle(&mut self, cycle: DayCycle) {
+        let time = cycle.time;
+        self.day_cycle = Some(cycle);
+        self.set_time_of_day(time);
+    }
+
+    /// Shadow rays per shading point toward each group of emissive cubes.
+    pub fn set_emitter_samples(&mut self, samples: u32) {
+        for light in &mut self.emitters {
+            light.samples = samples;
+        }
+    }
+
+    /// Moves the sun and moon to `hours` after midnight and updates `is_day` to
+    /// match. Does nothing without a `day_cycle`.
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        if let Some(cycle) = &mut self.day_cycle {
+            cycle.set_time(hours);
+            self.sky_lights = vec![cycle.sun(), cycle.moon()];
+            self.is_day = cycle.is_day();
+            self.update_ambient_tint();
+        }
+    }
+
+    pub fn time_of_day(&self) -> Option<f32> {
+        self.day_cycle.as_ref().map(|cycle| cycle.time)
+    }
+
+    /// Jumps to noon or midnight with a day cycle, or just flips `is_day` without one.
+    pub fn set_day(&mut self, is_day: bool) {
+        if self.day_cycle.is_some() {
+            self.set_time_of_day(if is_day { 12.0 } else { 0.0 });
+        } else {
+            self.is_day = is_day;
+            self.update_ambient_tint();
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Scene, SceneError> {
+        SceneDescription::load(path)?.build()
+    }
+
+    /// Replaces the object list and rebuilds the BVH.
+    pub fn set_objects(&mut self, objects: Vec<Box<dyn RayIntersect + Sync>>) {
+        self.bvh = Bvh::new(&objects);
+        self.objects = objects;
+    }
+
+    /// How much of the day lights is on, from 0.0 at night to 1.0 by day; the
+    /// night lights get the rest. Follows `daylight()` with a day cycle, so the
+    /// two lists cross-fade through dawn and dusk instead of swapping at once.
+    pub fn daylight(&self) -> f32 {
+        match &self.day_cycle {
+            Some(cycle) => cycle.daylight(),
+            None if self.is_day => 1.0,
+            None => 0.0,
+        }
+    }
+
+    /// Every light that is on right now, with how strongly: the day and night
+    /// lists weighted by `daylight`, the sun and moon and the emissive cubes.
+    pub fn lights(&self) -> impl Iterator<Item = (&dyn LightSource, f32)> {
+        let daylight = self.daylight();
+        let day = self.day_lights.iter().map(move |light| (light.as_ref(), daylight));
+        let night = self.night_lights.iter().map(move |light| (light.as_ref(), 1.0 - daylight));
+        day.chain(night)
+            .filter(|(_, strength)| *strength > 0.0)
+            .chain(self.sky_lights.iter().map(|light| (light as &dyn LightSource, 1.0)))
+            .chain(self.emitters.iter().map(|light| (light as &dyn LightSource, 1.0)))
+    }
+
+    pub fn sky_color(&self) -> Spectrum {
+        match &self.day_cycle {
+            Some(cycle) => cycle.sky_color(&self.sky),
+            None => Spectrum::from(self.sky.color(self.is_day)),
+        }
+    }
+
+    /// Ambient strength, as a fraction of `sky_color`.
+    pub fn ambient_factor(&self) -> f32 {
+        match &self.day_cycle {
+            Some(cycle) => cycle.ambient(&self.sky),
+            None => self.sky.ambient(self.is_day),
+        }
+    }
+
+    /// Ambient light: `ambient_factor` of the sky color, filtered by `ambient_tint`.
+    pub fn ambient_light(&self) -> Spectrum {
+        self.sky_color() * self.ambient_tint * self.ambient_factor()
+    }
+
+    /// Recomputes `ambient_tint` from the lights that are on: their colors,
+    /// each weighted by its power, so a scene lit by torches gets warm ambient
+    /// light and white lights leave it as is. The setters that change the
+    /// lights call it; call it after editing the light lists by hand.
+    pub fn update_ambient_tint(&mut self) {
+        let mut tint = Spectrum::black();
+        let mut total = 0.0;
+        for (light, strength) in self.lights() {
+            let weight = light.power() * strength;
+            tint += Spectrum::from(light.color()) * weight;
+            total += weight;
+        }
+        self.ambient_tint = if total > 0.0 { tint / total } else { Spectrum::gray(1.0) };
+    }
+
+    /// Closest hit along the ray, seeing through the holes of textures with
+    /// `AlphaMode::Mask`. After too many holes in a row the last surface counts
+    /// as solid, so thick foliage still blocks rays instead of vanishing.
+    pub fn intersect(&self, origin: &Vec3, direction: &Vec3) -> Intersect {
+        // Agujeros de texturas recortadas que un rayo puede cruzar antes de rendirse
+        const MAX_CUTOUT_SKIPS: u32 = 64;
+        // Cuanto avanza el rayo despues de un agujero, para no volver a chocar ahi
+        const CUTOUT_STEP: f32 = 1e-4;
+
+        let mut hit = self.bvh.intersect(&self.objects, origin, direction);
+        let mut skipped = 0.0;
+        for _ in 0..MAX_CUTOUT_SKIPS {
+            if !hit.is_intersecting || !hit.material.is_cut_out(hit.u, hit.v) {
+                break;
+            }
+            skipped += hit.distance + CUTOUT_STEP;
+            hit = self.bvh.intersect(&self.objects, &(origin + direction * skipped), direction);
+        }
+        hit.distance += skipped;
+        hit
+    }
+
+    pub fn occluded(&self, origin: &Vec3, direction: &Vec3, max_distance: f32) -> bool {
+        self.bvh.occluded(&self.objects, origin, direction, max_distance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_text(name: &str, text: &str) -> Result<SceneDescription, SceneError> {
+        let path = std::env::temp_dir().join(format!("ray_tracing_{}_{}.ron", name, std::process::id()));
+        fs::write(&path, text).unwrap();
+        let result = SceneDescription::load(&path.to_string_lossy());
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_bad_field() {
+        let text = "(\n    camera: (eye: (0.0, 0.0, 5.0), center: (0.0, 0.0, 0.0)),\n    objects: [\n        Sphere(center: (0.0, 0.0, 0.0), radius: \"big\", material: \"stone\"),\n    ],\n)\n";
+        match load_text("parse", text) {
+            Err(SceneError::Parse { line, col, field, .. }) => {
+                // Columna 49 de la linea 4: la comilla de "big"
+                assert_eq!((line, col), (4, 49));
+                assert_eq!(field, "objects[0].Sphere.radius");
+            }
+            Err(error) => panic!("unexpected error: {}", error),
+            Ok(_) => panic!("the scene should not parse"),
+        }
+    }
+}
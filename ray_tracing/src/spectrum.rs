@@ -0,0 +1,142 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub};
+use crate::color::Color;
+use crate::display::srgb_decode_u8;
+
+/// Linear RGB radiance in f32. Unlike `Color` nothing saturates here: values can
+/// go above 1.0 and only get squeezed into 8 bits by the display transform.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Spectrum {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Spectrum {
+    pub const fn new(r: f32, g: f32, b: f32) -> Spectrum {
+        Spectrum { r, g, b }
+    }
+
+    pub const fn black() -> Spectrum {
+        Spectrum::new(0.0, 0.0, 0.0)
+    }
+
+    pub const fn gray(value: f32) -> Spectrum {
+        Spectrum::new(value, value, value)
+    }
+
+    /// Decodes an sRGB-encoded 8-bit color (textures, colors written in scene
+    /// files) into linear values.
+    pub fn from_srgb(color: Color) -> Spectrum {
+        Spectrum::new(srgb_decode_u8(color.r), srgb_decode_u8(color.g), srgb_decode_u8(color.b))
+    }
+
+    /// Clamps to [0, 1] and quantizes to 8 bits per channel, with no transfer
+    /// curve. Use `DisplayTransform::apply` to go from radiance to a pixel.
+    pub fn to_color(self) -> Color {
+        let quantize = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::new(quantize(self.r), quantize(self.g), quantize(self.b))
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.r <= 0.0 && self.g <= 0.0 && self.b <= 0.0
+    }
+
+    pub fn max_component(&self) -> f32 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Relative luminance with Rec. 709 weights.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    pub fn map(self, f: impl Fn(f32) -> f32) -> Spectrum {
+        Spectrum::new(f(self.r), f(self.g), f(self.b))
+    }
+}
+
+impl From<Color> for Spectrum {
+    fn from(color: Color) -> Spectrum {
+        Spectrum::from_srgb(color)
+    }
+}
+
+impl Add for Spectrum {
+    type Output = Spectrum;
+
+    fn add(self, other: Spectrum) -> Spectrum {
+        Spectrum::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+impl AddAssign for Spectrum {
+    fn add_assign(&mut self, other: Spectrum) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Spectrum {
+    type Output = Spectrum;
+
+    fn sub(self, other: Spectrum) -> Spectrum {
+        Spectrum::new(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
+impl Mul for Spectrum {
+    type Output = Spectrum;
+
+    fn mul(self, other: Spectrum) -> Spectrum {
+        Spectrum::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl MulAssign for Spectrum {
+    fn mul_assign(&mut self, other: Spectrum) {
+        *self = *self * other;
+    }
+}
+
+impl Mul<f32> for Spectrum {
+    type Output = Spectrum;
+
+    fn mul(self, factor: f32) -> Spectrum {
+        Spectrum::new(self.r * factor, self.g * factor, self.b * factor)
+    }
+}
+
+impl Mul<Spectrum> for f32 {
+    type Output = Spectrum;
+
+    fn mul(self, spectrum: Spectrum) -> Spectrum {
+        spectrum * self
+    }
+}
+
+impl MulAssign<f32> for Spectrum {
+    fn mul_assign(&mut self, factor: f32) {
+        *self = *self * factor;
+    }
+}
+
+impl Div<f32> for Spectrum {
+    type Output = Spectrum;
+
+    fn div(self, divisor: f32) -> Spectrum {
+        Spectrum::new(self.r / divisor, self.g / divisor, self.b / divisor)
+    }
+}
+
+impl Sum for Spectrum {
+    fn sum<I: Iterator<Item = Spectrum>>(iter: I) -> Spectrum {
+        iter.fold(Spectrum::black(), |acc, s| acc + s)
+    }
+}
+
+impl fmt::Display for Spectrum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Spectrum(r: {}, g: {}, b: {})", self.r, self.g, self.b)
+    }
+}
@@ -0,0 +1,69 @@
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+use crate::bvh::Aabb;
+use crate::material::Material;
+use crate::rayintersect::{RayIntersect, Intersect};
+
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+    pub material: Material,
+}
+
+impl Sphere {
+    pub fn new(center: Vec3, radius: f32, material: Material) -> Self {
+        Sphere { center, radius, material }
+    }
+
+    /// Equirectangular mapping: u goes around the equator, v from the top pole to the bottom one.
+    pub fn get_uv(&self, normal: &Vec3) -> (f32, f32) {
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+        let v = 0.5 - normal.y.clamp(-1.0, 1.0).asin() / PI;
+        (u, v)
+    }
+
+    /// Directions in which `u` and `v` grow at `normal`: east along the
+    /// parallel and south along the meridian. Zero at the poles.
+    pub fn tangents(normal: &Vec3) -> (Vec3, Vec3) {
+        let around = Vec3::new(-normal.z, 0.0, normal.x);
+        if around.norm_squared() < 1e-12 {
+            return (Vec3::zeros(), Vec3::zeros());
+        }
+        let tangent = around.normalize();
+        (tangent, normal.cross(&tangent))
+    }
+}
+
+impl RayIntersect for Sphere {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let oc = ray_origin - self.center;
+        let a = ray_direction.dot(ray_direction);
+        let b = oc.dot(ray_direction);
+        let c = oc.dot(&oc) - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+
+        if discriminant < 0.0 {
+            return Intersect::empty();
+        }
+
+        let sqrt_d = discriminant.sqrt();
+        let t_near = (-b - sqrt_d) / a;
+        let t_far = (-b + sqrt_d) / a;
+        // Igual que Cube: si el origen esta adentro usamos la salida
+        let distance = if t_near >= 0.0 { t_near } else { t_far };
+        if distance < 0.0 {
+            return Intersect::empty();
+        }
+
+        let point = ray_origin + ray_direction * distance;
+        let normal = (point - self.center).normalize();
+        let (u, v) = self.get_uv(&normal);
+        let (tangent, bitangent) = Sphere::tangents(&normal);
+        Intersect::new(point, normal, distance, self.material.clone(), u, v).with_tangents(tangent, bitangent)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
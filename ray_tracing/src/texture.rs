@@ -1,22 +1,142 @@
 
-use image::{ImageReader, Pixel};
+use image::{ImageReader, ImageResult};
 use image::{DynamicImage, GenericImageView};
+use std::ops::{Add, Mul};
 use crate::color::Color;
+use crate::sampler::{Sampler, TextureFilter, WrapMode};
+use crate::spectrum::Spectrum;
 
+/// One level of the mip chain, in linear RGB plus coverage.
+struct MipLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<Spectrum>,
+    alpha: Vec<f32>,
+}
+
+impl MipLevel {
+    fn texel(&self, x: u32, y: u32) -> Spectrum {
+        self.texels[(y * self.width + x) as usize]
+    }
+
+    fn alpha(&self, x: u32, y: u32) -> f32 {
+        self.alpha[(y * self.width + x) as usize]
+    }
+
+    /// First texel and size in texels of `region` at this level.
+    fn span(&self, region: TextureRegion) -> (u32, u32, u32, u32) {
+        let x = ((region.x * self.width as f32).floor() as u32).min(self.width - 1);
+        let y = ((region.y * self.height as f32).floor() as u32).min(self.height - 1);
+        let width = ((region.width * self.width as f32).round() as u32).clamp(1, self.width - x);
+        let height = ((region.height * self.height as f32).round() as u32).clamp(1, self.height - y);
+        (x, y, width, height)
+    }
+
+    /// Half the size, each texel the average of the 2x2 block above it.
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut texels = Vec::with_capacity((width * height) as usize);
+        let mut alpha = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                // En tamaños impares el ultimo texel se repite
+                let (x0, y0) = ((2 * x).min(self.width - 1), (2 * y).min(self.height - 1));
+                let (x1, y1) = ((2 * x + 1).min(self.width - 1), (2 * y + 1).min(self.height - 1));
+                let sum = self.texel(x0, y0) + self.texel(x1, y0) + self.texel(x0, y1) + self.texel(x1, y1);
+                texels.push(sum / 4.0);
+                alpha.push((self.alpha(x0, y0) + self.alpha(x1, y0) + self.alpha(x0, y1) + self.alpha(x1, y1)) / 4.0);
+            }
+        }
+        MipLevel { width, height, texels, alpha }
+    }
+}
+
+/// Part of a texture in UV units, such as one tile of an atlas. Lookups
+/// inside it wrap and pick mip levels as if it were a texture of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl TextureRegion {
+    pub const FULL: TextureRegion = TextureRegion { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    /// Tile at `column`, `row` of an atlas cut into `columns` x `rows` equal
+    /// tiles, counting from the top left like Minecraft's terrain.png.
+    pub fn tile(column: u32, row: u32, columns: u32, rows: u32) -> TextureRegion {
+        let (width, height) = (1.0 / columns as f32, 1.0 / rows as f32);
+        TextureRegion { x: column as f32 * width, y: row as f32 * height, width, height }
+    }
+}
+
+impl Default for TextureRegion {
+    fn default() -> Self {
+        TextureRegion::FULL
+    }
+}
 
 pub struct Texture {
     image: DynamicImage,
     pub width: u32,
-    pub height: u32
+    pub height: u32,
+    /// Level 0 is the image itself; each next one is half the size, down to 1x1.
+    mips: Vec<MipLevel>,
 }
 
 impl Texture {
     pub fn new(file_path: &str) -> Texture {
-        let img = ImageReader::open(file_path).unwrap().decode().unwrap();
+        Texture::load(file_path).unwrap()
+    }
+
+    pub fn load(file_path: &str) -> ImageResult<Texture> {
+        let img = ImageReader::open(file_path)?.decode()?;
+        Ok(Texture::from_image(img))
+    }
+
+    /// Loads a texture that stores data rather than color, such as a normal
+    /// or specular map, so its values are not decoded from sRGB.
+    pub fn load_linear(file_path: &str) -> ImageResult<Texture> {
+        let img = ImageReader::open(file_path)?.decode()?;
+        Ok(Texture::from_image_linear(img))
+    }
+
+    /// Wraps a decoded image and builds its mip chain.
+    pub fn from_image(img: DynamicImage) -> Texture {
+        Texture::build(img, true)
+    }
+
+    /// Like `from_image`, with texel values taken as they are (0-255 to 0-1).
+    pub fn from_image_linear(img: DynamicImage) -> Texture {
+        Texture::build(img, false)
+    }
+
+    fn build(img: DynamicImage, srgb: bool) -> Texture {
         let width = img.width();
         let height = img.height();
 
-        Texture{image:img, width, height}
+        let texels = img
+            .pixels()
+            .map(|(_, _, pixel)| {
+                if srgb {
+                    Spectrum::from_srgb(Color::new(pixel[0], pixel[1], pixel[2]))
+                } else {
+                    Spectrum::new(pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0)
+                }
+            })
+            .collect();
+        // Las imagenes sin canal alfa llegan con 255 en todos los pixeles
+        let alpha = img.pixels().map(|(_, _, pixel)| pixel[3] as f32 / 255.0).collect();
+        let mut mips = vec![MipLevel { width, height, texels, alpha }];
+        while let Some(last) = mips.last().filter(|level| level.width > 1 || level.height > 1) {
+            let next = last.downsample();
+            mips.push(next);
+        }
+
+        Texture{image:img, width, height, mips}
     }
 
     pub fn get_pixel_color(&self, x:u32, y:u32) -> Color{
@@ -26,16 +146,122 @@ impl Texture {
 
         let pixel = self.image.get_pixel(x,y);
         Color::new(pixel[0], pixel[1], pixel[2])
-        
+
     }
 
-    pub fn get_color(&self, u: f32, v: f32) -> Color {
-        let width = self.image.width();
-        let height = self.image.height();
-        let x = (u * width as f32).clamp(0.0, width as f32 - 1.0) as u32;
-        let y = (v * height as f32).clamp(0.0, height as f32 - 1.0) as u32;
+    /// Texel at (u, v) of the full-size image, decoded from sRGB to linear.
+    pub fn get_color(&self, u: f32, v: f32) -> Spectrum {
+        let sampler = Sampler { filter: TextureFilter::Nearest, wrap: WrapMode::Clamp };
+        self.sample(&sampler, u, v, 0.0)
+    }
 
-        let pixel = self.image.get_pixel(x, y);
-        Color::new(pixel[0], pixel[1], pixel[2])
+    /// Filtered color at (u, v). `footprint` is how much of the texture, in
+    /// UV units, one pixel covers there; it picks the mip level.
+    pub fn sample(&self, sampler: &Sampler, u: f32, v: f32, footprint: f32) -> Spectrum {
+        self.sample_region(sampler, TextureRegion::FULL, u, v, footprint)
+    }
+
+    /// Like `sample`, with (u, v) and `footprint` relative to `region`. Mip
+    /// levels stop where the region would shrink below one texel, and a
+    /// region smaller than the image clamps to its edges whatever the
+    /// sampler's wrap mode, so neighbouring atlas tiles don't bleed into
+    /// each other.
+    pub fn sample_region(&self, sampler: &Sampler, region: TextureRegion, u: f32, v: f32, footprint: f32) -> Spectrum {
+        self.filtered(sampler, region, u, v, footprint, MipLevel::texel)
+    }
+
+    /// Filtered alpha at (u, v) of `region`: 0.0 where the image is cut out,
+    /// 1.0 where it is solid or has no alpha channel.
+    pub fn sample_alpha(&self, sampler: &Sampler, region: TextureRegion, u: f32, v: f32, footprint: f32) -> f32 {
+        self.filtered(sampler, region, u, v, footprint, MipLevel::alpha)
+    }
+
+    fn filtered<T>(
+        &self,
+        sampler: &Sampler,
+        region: TextureRegion,
+        u: f32,
+        v: f32,
+        footprint: f32,
+        fetch: fn(&MipLevel, u32, u32) -> T,
+    ) -> T
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        // Fuera de la imagen completa, repetir mezclaria el tile vecino del atlas
+        let wrap = if region == TextureRegion::FULL { sampler.wrap } else { WrapMode::Clamp };
+        let tile_width = (region.width * self.width as f32).max(1.0);
+        let tile_height = (region.height * self.height as f32).max(1.0);
+        let last = ((self.mips.len() - 1) as f32).min(tile_width.min(tile_height).log2().floor());
+        let lod = (footprint * tile_width.max(tile_height)).max(1e-8).log2().clamp(0.0, last);
+        match sampler.filter {
+            TextureFilter::Nearest => self.nearest(lod.round() as usize, wrap, region, u, v, fetch),
+            TextureFilter::Bilinear => self.bilinear(lod.round() as usize, wrap, region, u, v, fetch),
+            TextureFilter::Trilinear => {
+                let level = lod.floor() as usize;
+                let t = lod - level as f32;
+                let fine = self.bilinear(level, wrap, region, u, v, fetch);
+                if t <= 0.0 {
+                    return fine;
+                }
+                fine * (1.0 - t) + self.bilinear(level + 1, wrap, region, u, v, fetch) * t
+            }
+        }
     }
-}
\ No newline at end of file
+
+    fn nearest<T>(&self, level: usize, wrap: WrapMode, region: TextureRegion, u: f32, v: f32, fetch: fn(&MipLevel, u32, u32) -> T) -> T {
+        let mip = &self.mips[level];
+        let (x0, y0, width, height) = mip.span(region);
+        let x = x0 + wrap.apply((u * width as f32).floor() as i64, width);
+        let y = y0 + wrap.apply((v * height as f32).floor() as i64, height);
+        fetch(mip, x, y)
+    }
+
+    fn bilinear<T>(&self, level: usize, wrap: WrapMode, region: TextureRegion, u: f32, v: f32, fetch: fn(&MipLevel, u32, u32) -> T) -> T
+    where
+        T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+    {
+        let mip = &self.mips[level];
+        let (tile_x, tile_y, width, height) = mip.span(region);
+        // Centros de texel en x + 0.5
+        let x = u * width as f32 - 0.5;
+        let y = v * height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let texel = |dx: i64, dy: i64| fetch(mip, tile_x + wrap.apply(x0 + dx, width), tile_y + wrap.apply(y0 + dy, height));
+        let top = texel(0, 0) * (1.0 - fx) + texel(1, 0) * fx;
+        let bottom = texel(0, 1) * (1.0 - fx) + texel(1, 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_covers_its_cell_of_the_atlas() {
+        let tile = TextureRegion::tile(2, 4, 16, 16);
+        assert_eq!(tile, TextureRegion { x: 0.125, y: 0.25, width: 0.0625, height: 0.0625 });
+        assert_eq!(TextureRegion::tile(0, 0, 1, 1), TextureRegion::FULL);
+        let last = TextureRegion::tile(3, 1, 4, 2);
+        assert_eq!((last.x + last.width, last.y + last.height), (1.0, 1.0));
+    }
+
+    #[test]
+    fn atlas_tiles_clamp_to_their_own_texels() {
+        let mut image = image::RgbImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        image.put_pixel(1, 0, image::Rgb([0, 0, 255]));
+        let texture = Texture::from_image(DynamicImage::ImageRgb8(image));
+        let sampler = Sampler { filter: TextureFilter::Bilinear, wrap: WrapMode::Repeat };
+        let left = TextureRegion::tile(0, 0, 2, 1);
+        for u in [0.0, 0.5, 0.99, 1.3, -0.2] {
+            assert_eq!(texture.sample_region(&sampler, left, u, 0.5, 0.0).b, 0.0);
+        }
+        // La imagen completa si repite: el borde derecho mezcla el texel izquierdo
+        assert!(texture.sample(&sampler, 0.99, 0.5, 0.0).r > 0.0);
+    }
+}
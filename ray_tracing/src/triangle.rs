@@ -0,0 +1,118 @@
+use nalgebra_glm::{Vec2, Vec3};
+use std::sync::Arc;
+use crate::bvh::Aabb;
+use crate::material::Material;
+use crate::rayintersect::{RayIntersect, Intersect};
+
+const EPSILON: f32 = 1e-7;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+}
+
+impl Vertex {
+    pub fn new(position: Vec3, normal: Vec3, uv: Vec2) -> Self {
+        Vertex { position, normal, uv }
+    }
+}
+
+/// A triangle with per-vertex normals and UVs. The material is shared through an
+/// `Arc` so the thousands of triangles of a mesh don't each carry a copy.
+#[derive(Clone)]
+pub struct Triangle {
+    pub vertices: [Vertex; 3],
+    pub material: Arc<Material>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Vertex; 3], material: Arc<Material>) -> Self {
+        Triangle { vertices, material }
+    }
+
+    /// Triangle without shading normals: every vertex gets the face normal.
+    pub fn flat(a: Vec3, b: Vec3, c: Vec3, material: Arc<Material>) -> Self {
+        let normal = (b - a).cross(&(c - a)).normalize();
+        Triangle::new(
+            [
+                Vertex::new(a, normal, Vec2::new(0.0, 0.0)),
+                Vertex::new(b, normal, Vec2::new(1.0, 0.0)),
+                Vertex::new(c, normal, Vec2::new(0.0, 1.0)),
+            ],
+            material,
+        )
+    }
+
+    /// Directions in which `u` and `v` grow across the triangle, from its UVs.
+    /// Zero when the UVs are degenerate and give no frame.
+    pub fn tangents(&self) -> (Vec3, Vec3) {
+        let [v0, v1, v2] = &self.vertices;
+        let (edge1, edge2) = (v1.position - v0.position, v2.position - v0.position);
+        let (duv1, duv2) = (v1.uv - v0.uv, v2.uv - v0.uv);
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() < EPSILON {
+            return (Vec3::zeros(), Vec3::zeros());
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+        (tangent.normalize(), bitangent.normalize())
+    }
+
+    /// Möller-Trumbore. Returns the distance and the barycentric weights of the
+    /// second and third vertices.
+    pub fn hit(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<(f32, f32, f32)> {
+        let [v0, v1, v2] = &self.vertices;
+        let edge1 = v1.position - v0.position;
+        let edge2 = v2.position - v0.position;
+        let p = ray_direction.cross(&edge2);
+        let det = edge1.dot(&p);
+        if det.abs() < EPSILON {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = ray_origin - v0.position;
+        let b1 = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let b2 = ray_direction.dot(&q) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(&q) * inv_det;
+        if distance < 0.0 {
+            return None;
+        }
+        Some((distance, b1, b2))
+    }
+}
+
+impl RayIntersect for Triangle {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let (distance, b1, b2) = match self.hit(ray_origin, ray_direction) {
+            Some(hit) => hit,
+            None => return Intersect::empty(),
+        };
+
+        let b0 = 1.0 - b1 - b2;
+        let [v0, v1, v2] = &self.vertices;
+        let point = ray_origin + ray_direction * distance;
+        let normal = (v0.normal * b0 + v1.normal * b1 + v2.normal * b2).normalize();
+        let uv = v0.uv * b0 + v1.uv * b1 + v2.uv * b2;
+        let (tangent, bitangent) = self.tangents();
+        Intersect::new(point, normal, distance, (*self.material).clone(), uv.x, uv.y)
+            .with_tangents(tangent, bitangent)
+            .thin()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [v0, v1, v2] = &self.vertices;
+        Some(Aabb::new(v0.position, v1.position).union(&Aabb::new(v2.position, v2.position)))
+    }
+}
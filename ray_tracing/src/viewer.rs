@@ -0,0 +1,110 @@
+use minifb::{Window, WindowOptions, Key, KeyRepeat};
+use nalgebra_glm::Vec3;
+use ray_tracing::{Color, FrameBuffer, Integrator, Renderer, Scene};
+use std::error::Error;
+use std::f32::consts::PI;
+use std::time::Duration;
+
+const ZOOM:f32 = 0.5;
+// Pasadas a acumular con la vista quieta antes de dejar de renderizar
+const MAX_ACCUMULATED_SAMPLES: u32 = 1024;
+// Horas que avanza el dia por cada frame con T o R apretada
+const TIME_STEP: f32 = 0.1;
+
+pub fn view(scene_path: &str) -> Result<(), Box<dyn Error>> {
+    let mut scene = Scene::load(scene_path)?;
+
+    let window_height = 600;
+    let window_width = 800;
+
+    let framebuffer_height = 600;
+    let framebuffer_width = 800;
+
+    let frame_delay = Duration::from_millis(0);
+
+    let mut framebuffer = FrameBuffer::new(framebuffer_width, framebuffer_height);
+    framebuffer.set_background_color(Color::new(128,128,128));
+    framebuffer.display = scene.display;
+
+    let rotation_speed = PI/60.0;
+    // En la ventana el path tracer va a una muestra por pixel para seguir siendo interactivo
+    let mut renderer = Renderer { samples: 1, ..Renderer::default() };
+    let mut window = Window::new(
+        "Minecraft RayTracer",
+        window_width,
+        window_height,
+        WindowOptions::default(),
+    )?;
+    
+    // Cambios que no son de la camara pero igual invalidan lo acumulado
+    let mut lighting_changed = true;
+    while window.is_open() {
+        if window.is_key_down(Key::Escape){
+            break;
+        }
+        if window.is_key_down(Key::Left){
+            scene.camera.orbit(rotation_speed, 0.0);
+        }
+        if window.is_key_down(Key::Right){
+            scene.camera.orbit(-rotation_speed, 0.0);
+        }
+        if window.is_key_down(Key::Up){
+            scene.camera.orbit(0.0, -rotation_speed);
+        }
+        if window.is_key_down(Key::Down){
+            scene.camera.orbit(0.0, rotation_speed);
+        }
+        if window.is_key_down(Key::W){
+            scene.camera.zoom(ZOOM);
+        }
+        if window.is_key_down(Key::S){
+            scene.camera.zoom(-ZOOM);
+        }
+        if window.is_key_down(Key::N){
+            scene.set_day(!scene.is_day);
+            lighting_changed = true;
+        }
+        if let Some(time) = scene.time_of_day() {
+            if window.is_key_down(Key::T){
+                scene.set_time_of_day(time + TIME_STEP);
+                lighting_changed = true;
+            }
+            if window.is_key_down(Key::R){
+                scene.set_time_of_day(time - TIME_STEP);
+                lighting_changed = true;
+            }
+        }
+
+        if window.is_key_pressed(Key::P, KeyRepeat::No){
+            renderer.integrator = match renderer.integrator {
+                Integrator::Whitted => Integrator::PathTracer,
+                Integrator::PathTracer => Integrator::Whitted,
+            };
+            lighting_changed = true;
+        }
+
+        if window.is_key_down(Key::D){
+            scene.camera.move_center(Vec3::new(1.1, 1.0, 1.0));
+        }
+
+        if scene.camera.check_if_changed() || lighting_changed {
+            framebuffer.reset_accumulation();
+            lighting_changed = false;
+        }
+        if framebuffer.sample_count < MAX_ACCUMULATED_SAMPLES {
+            renderer.render_progressive(&mut framebuffer, &scene);
+            let time = scene.time_of_day().map(|hours| format!(", {:02}:{:02}", hours as u32, (hours.fract() * 60.0) as u32));
+            window.set_title(&format!(
+                "Minecraft RayTracer - {} ({} spp{})",
+                renderer.integrator,
+                framebuffer.sample_count,
+                time.unwrap_or_default()
+            ));
+        }
+
+
+        window.update_with_buffer(&framebuffer.cast_buffer(), framebuffer_width, framebuffer_height)?;
+        std::thread::sleep(frame_delay);
+    }
+    Ok(())
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::fmt;
+use nalgebra_glm::Vec3;
+use crate::bvh::Aabb;
+use crate::cube::{face_tangents, face_uv, Cube};
+use crate::material::Material;
+use crate::rayintersect::{RayIntersect, Intersect};
+
+pub type BlockId = u16;
+pub const AIR: BlockId = 0;
+
+/// A world already uses every block ID, so a new material has no room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteFull;
+
+impl fmt::Display for PaletteFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a voxel world holds at most {} different materials", BlockId::MAX)
+    }
+}
+
+impl std::error::Error for PaletteFull {}
+
+const CHUNK_SIZE: i32 = 16;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+struct Chunk {
+    blocks: Vec<BlockId>,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk { blocks: vec![AIR; CHUNK_VOLUME] }
+    }
+
+    fn index(local: [i32; 3]) -> usize {
+        (local[0] + CHUNK_SIZE * (local[1] + CHUNK_SIZE * local[2])) as usize
+    }
+}
+
+/// Blocks on a 1.0-unit lattice, stored as block IDs in 16x16x16 chunks.
+/// Block (x, y, z) covers `origin + [x, x+1] x [y, y+1] x [z, z+1]`; the
+/// material for each ID lives in a palette, with ID 0 reserved for air.
+pub struct VoxelWorld {
+    origin: Vec3,
+    palette: Vec<Material>,
+    chunks: HashMap<[i32; 3], Chunk>,
+    // Limites (inclusivos) de los bloques colocados, para recortar los rayos
+    min_block: [i32; 3],
+    max_block: [i32; 3],
+    block_count: usize,
+}
+
+impl VoxelWorld {
+    pub fn new(origin: Vec3) -> Self {
+        VoxelWorld {
+            origin,
+            palette: Vec::new(),
+            chunks: HashMap::new(),
+            min_block: [i32::MAX; 3],
+            max_block: [i32::MIN; 3],
+            block_count: 0,
+        }
+    }
+
+    /// Builds a world out of unit cubes that sit on the lattice defined by `origin`.
+    /// Per-cube materials are kept, with cubes of the same material sharing a block ID.
+    pub fn from_cubes(origin: Vec3, cubes: &[Cube]) -> Result<Self, PaletteFull> {
+        let mut world = VoxelWorld::new(origin);
+        for cube in cubes {
+            let center = Aabb::new(cube.min, cube.max).centroid() - origin;
+            let id = world.add_block_type(cube.material.clone())?;
+            world.set_block(center.x.floor() as i32, center.y.floor() as i32, center.z.floor() as i32, id);
+        }
+        Ok(world)
+    }
+
+    /// Registers a material and returns the block ID that refers to it. A
+    /// material that is already in the palette keeps its ID.
+    pub fn add_block_type(&mut self, material: Material) -> Result<BlockId, PaletteFull> {
+        if let Some(index) = self.palette.iter().position(|known| *known == material) {
+            return Ok(index as BlockId + 1);
+        }
+        // El ID 0 es el aire, asi que caben BlockId::MAX materiales
+        if self.palette.len() >= BlockId::MAX as usize {
+            return Err(PaletteFull);
+        }
+        self.palette.push(material);
+        Ok(self.palette.len() as BlockId)
+    }
+
+    pub fn material(&self, id: BlockId) -> Option<&Material> {
+        if id == AIR {
+            None
+        } else {
+            self.palette.get(id as usize - 1)
+        }
+    }
+
+    /// Every block whose material glows, as a unit cube in world space, so
+    /// the scene can light with them.
+    pub fn emissive_cubes(&self) -> Vec<Cube> {
+        let mut cubes = Vec::new();
+        for (key, chunk) in &self.chunks {
+            for (index, &id) in chunk.blocks.iter().enumerate() {
+                let material = match self.material(id) {
+                    Some(material) if material.emission_strength > 0.0 => material,
+                    _ => continue,
+                };
+                let index = index as i32;
+                let local = [index % CHUNK_SIZE, (index / CHUNK_SIZE) % CHUNK_SIZE, index / (CHUNK_SIZE * CHUNK_SIZE)];
+                let min = self.origin + Vec3::new(
+                    (key[0] * CHUNK_SIZE + local[0]) as f32,
+                    (key[1] * CHUNK_SIZE + local[1]) as f32,
+                    (key[2] * CHUNK_SIZE + local[2]) as f32,
+                );
+                cubes.push(Cube { min, max: min + Vec3::new(1.0, 1.0, 1.0), material: material.clone() });
+            }
+        }
+        cubes
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.block_count
+    }
+
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let (key, local) = split(x, y, z);
+        match self.chunks.get(&key) {
+            Some(chunk) => chunk.blocks[Chunk::index(local)],
+            None => AIR,
+        }
+    }
+
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, id: BlockId) {
+        let (key, local) = split(x, y, z);
+        if id == AIR && !self.chunks.contains_key(&key) {
+            return;
+        }
+
+        let slot = &mut self.chunks.entry(key).or_insert_with(Chunk::new).blocks[Chunk::index(local)];
+        match (*slot == AIR, id == AIR) {
+            (true, false) => self.block_count += 1,
+            (false, true) => self.block_count -= 1,
+            _ => {}
+        }
+        *slot = id;
+
+        if id != AIR {
+            for (axis, value) in [x, y, z].into_iter().enumerate() {
+                self.min_block[axis] = self.min_block[axis].min(value);
+                self.max_block[axis] = self.max_block[axis].max(value);
+            }
+        }
+    }
+
+    /// Fills the inclusive box of blocks between `min` and `max`.
+    pub fn fill(&mut self, min: [i32; 3], max: [i32; 3], id: BlockId) {
+        for z in min[2]..=max[2] {
+            for y in min[1]..=max[1] {
+                for x in min[0]..=max[0] {
+                    self.set_block(x, y, z, id);
+                }
+            }
+        }
+    }
+
+    fn bounds(&self) -> Option<Aabb> {
+        if self.block_count == 0 {
+            return None;
+        }
+        let min = Vec3::new(self.min_block[0] as f32, self.min_block[1] as f32, self.min_block[2] as f32);
+        let max = Vec3::new(self.max_block[0] as f32, self.max_block[1] as f32, self.max_block[2] as f32);
+        Some(Aabb::new(self.origin + min, self.origin + max + Vec3::new(1.0, 1.0, 1.0)))
+    }
+
+    fn block_hit(&self, id: BlockId, cell: [i32; 3], point: Vec3, normal: Vec3, distance: f32) -> Intersect {
+        let block_min = self.origin + Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32);
+        let (u, v) = face_uv(point - block_min, normal);
+        let material = self.material(id).map(|material| material.on_face(normal)).unwrap_or_else(Material::black);
+        let (tangent, bitangent) = face_tangents(normal);
+        let emitter = material.emission_strength > 0.0;
+        Intersect::new(point, normal, distance, material, u, v).with_tangents(tangent, bitangent).emitter(emitter)
+    }
+}
+
+fn split(x: i32, y: i32, z: i32) -> ([i32; 3], [i32; 3]) {
+    (
+        [x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE), z.div_euclid(CHUNK_SIZE)],
+        [x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE), z.rem_euclid(CHUNK_SIZE)],
+    )
+}
+
+fn axis_normal(axis: usize, sign: f32) -> Vec3 {
+    let mut normal = Vec3::zeros();
+    normal[axis] = sign;
+    normal
+}
+
+impl RayIntersect for VoxelWorld {
+    /// Amanatides-Woo traversal: clip the ray to the occupied region, then step
+    /// cell by cell along whichever axis reaches its next boundary first.
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let bounds = match self.bounds() {
+            Some(bounds) => bounds,
+            None => return Intersect::empty(),
+        };
+        let inv_dir = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let (t_enter, t_exit) = match bounds.clip(ray_origin, &inv_dir) {
+            Some(range) => range,
+            None => return Intersect::empty(),
+        };
+        let t_start = t_enter.max(0.0);
+
+        // Coordenadas relativas a la esquina del bloque (0, 0, 0)
+        let local_origin = ray_origin - self.origin;
+        let start = local_origin + ray_direction * t_start;
+
+        let mut cell = [0i32; 3];
+        let mut step = [0i32; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for axis in 0..3 {
+            cell[axis] = (start[axis].floor() as i32).clamp(self.min_block[axis], self.max_block[axis]);
+            if ray_direction[axis] > 0.0 {
+                step[axis] = 1;
+                t_max[axis] = ((cell[axis] + 1) as f32 - local_origin[axis]) * inv_dir[axis];
+                t_delta[axis] = inv_dir[axis];
+            } else if ray_direction[axis] < 0.0 {
+                step[axis] = -1;
+                t_max[axis] = (cell[axis] as f32 - local_origin[axis]) * inv_dir[axis];
+                t_delta[axis] = -inv_dir[axis];
+            }
+        }
+
+        // Cara por la que entramos a la celda actual. `None` si el rayo empieza adentro.
+        let mut entry_axis = if t_enter > 0.0 {
+            (0..3)
+                .filter(|&axis| step[axis] != 0)
+                .max_by(|&a, &b| {
+                    let near_a = t_max[a] - t_delta[a];
+                    let near_b = t_max[b] - t_delta[b];
+                    near_a.total_cmp(&near_b)
+                })
+        } else {
+            None
+        };
+        let mut t_current = t_start;
+
+        loop {
+            let id = self.get_block(cell[0], cell[1], cell[2]);
+            if id != AIR {
+                return match entry_axis {
+                    Some(axis) => {
+                        let normal = axis_normal(axis, -step[axis] as f32);
+                        let point = ray_origin + ray_direction * t_current;
+                        self.block_hit(id, cell, point, normal, t_current)
+                    }
+                    None => {
+                        // Igual que Cube: si empezamos dentro de un bloque reportamos la cara de salida
+                        let axis = next_axis(&t_max);
+                        let normal = axis_normal(axis, step[axis] as f32);
+                        let point = ray_origin + ray_direction * t_max[axis];
+                        self.block_hit(id, cell, point, normal, t_max[axis])
+                    }
+                };
+            }
+
+            let axis = next_axis(&t_max);
+            t_current = t_max[axis];
+            if t_current > t_exit {
+                return Intersect::empty();
+            }
+            cell[axis] += step[axis];
+            if cell[axis] < self.min_block[axis] || cell[axis] > self.max_block[axis] {
+                return Intersect::empty();
+            }
+            t_max[axis] += t_delta[axis];
+            entry_axis = Some(axis);
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds()
+    }
+}
+
+fn next_axis(t_max: &[f32; 3]) -> usize {
+    if t_max[0] <= t_max[1] && t_max[0] <= t_max[2] {
+        0
+    } else if t_max[1] <= t_max[2] {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single_block() -> VoxelWorld {
+        let mut world = VoxelWorld::new(Vec3::zeros());
+        let id = world.add_block_type(Material::black()).unwrap();
+        world.set_block(0, 0, 0, id);
+        world
+    }
+
+    #[test]
+    fn hits_report_face_normal_and_uv() {
+        let world = single_block();
+        // (origen, direccion, normal esperada, uv esperado)
+        let cases = [
+            (Vec3::new(0.25, 0.75, -1.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0), (0.25, 0.25)),
+            (Vec3::new(0.3, 2.0, 0.6), Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), (0.3, 0.6)),
+            (Vec3::new(2.0, 0.2, 0.4), Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), (0.4, 0.8)),
+        ];
+        for (origin, direction, normal, (u, v)) in cases {
+            let hit = world.ray_intersect(&origin, &direction);
+            assert!(hit.is_intersecting);
+            assert_eq!(hit.normal, normal);
+            assert!((hit.u - u).abs() < 1e-5 && (hit.v - v).abs() < 1e-5, "uv ({}, {})", hit.u, hit.v);
+        }
+    }
+
+    #[test]
+    fn matches_cube_intersection() {
+        let world = single_block();
+        let cube = Cube { min: Vec3::zeros(), max: Vec3::new(1.0, 1.0, 1.0), material: Material::black() };
+        let origin = Vec3::new(-1.0, 1.7, -0.6);
+        let direction = Vec3::new(1.0, -1.0, 0.9).normalize();
+        let expected = cube.ray_intersect(&origin, &direction);
+        let hit = world.ray_intersect(&origin, &direction);
+        assert!(expected.is_intersecting && hit.is_intersecting);
+        assert_eq!(hit.normal, expected.normal);
+        assert!((hit.distance - expected.distance).abs() < 1e-5);
+        assert!((hit.u - expected.u).abs() < 1e-5 && (hit.v - expected.v).abs() < 1e-5);
+    }
+
+    #[test]
+    fn identical_materials_share_a_block_id() {
+        let mut world = VoxelWorld::new(Vec3::zeros());
+        let first = world.add_block_type(Material::black()).unwrap();
+        assert_eq!(world.add_block_type(Material::black()), Ok(first));
+    }
+}